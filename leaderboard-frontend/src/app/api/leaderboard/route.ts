@@ -1,8 +1,8 @@
 import { NextRequest, NextResponse } from 'next/server';
 import { LeaderboardParams, LeaderboardData, TraderStats } from '@/types';
 
-// Hardcoded API key for The Graph Network
-const GRAPH_API_KEY = 'e945e8b23d8af7b0f249e0a260e6768d';
+// API key for The Graph Network, read from the server environment (no built-in fallback)
+const GRAPH_API_KEY = process.env.GRAPH_API_KEY;
 const UNISWAP_SUBGRAPH_URL = `https://gateway.thegraph.com/api/${GRAPH_API_KEY}/subgraphs/id/5zvR82QoaXYFyDEKLZ9t6v9adgnptxYpKpSbxtgVENFV`;
 
 interface SwapData {
@@ -85,6 +85,10 @@ async function fetchLeaderboardFromGraph(params: LeaderboardParams): Promise<Lea
   let skip = 0;
   const batchSize = 1000;
 
+  if (!GRAPH_API_KEY) {
+    throw new Error('GRAPH_API_KEY is not set');
+  }
+
   // Fetch swaps in batches
   while (allSwaps.length < 10000) { // Limit to prevent timeout
     const response = await fetch(UNISWAP_SUBGRAPH_URL, {
@@ -194,7 +198,7 @@ async function fetchLeaderboardFromGraph(params: LeaderboardParams): Promise<Lea
       total_volume_usd: (stats.total_buy_volume_usd + stats.total_sell_volume_usd).toFixed(2),
       net_volume_token: (stats.total_buy_volume_token - stats.total_sell_volume_token >= 0 ? '+' : '') + 
                        (stats.total_buy_volume_token - stats.total_sell_volume_token).toFixed(4),
-      buy_sell_ratio: stats.total_sells > 0 ? stats.total_buys / stats.total_sells : stats.total_buys,
+      buy_sell_ratio: stats.total_sells > 0 ? stats.total_buys / stats.total_sells : null,
     }))
     .sort((a, b) => parseFloat(b.total_volume_usd) - parseFloat(a.total_volume_usd))
     .slice(0, params.limit || 20);
@@ -290,7 +294,7 @@ export async function POST(request: NextRequest) {
     const rustBackendUrl = process.env.RUST_BACKEND_URL || 'http://localhost:3001';
     
     try {
-      const response = await fetch(`${rustBackendUrl}/api/leaderboard`, {
+      const response = await fetch(`${rustBackendUrl}/api/v1/leaderboard`, {
         method: 'POST',
         headers: {
           'Content-Type': 'application/json',
@@ -300,6 +304,12 @@ export async function POST(request: NextRequest) {
       });
 
       if (!response.ok) {
+        // Structured backend errors are passed through so the client can tell
+        // an upstream outage from a bad request or an empty leaderboard
+        const body = await response.json().catch(() => null);
+        if (body && typeof body.code === 'string') {
+          return NextResponse.json(body, { status: response.status });
+        }
         throw new Error(`Backend error: ${response.status}`);
       }
 
@@ -418,8 +418,8 @@ export default function Leaderboard() {
                       </span>
                     </TableCell>
                     <TableCell>
-                      <Badge variant={trader.buy_sell_ratio > 1 ? "default" : "outline"}>
-                        {trader.buy_sell_ratio.toFixed(2)}
+                      <Badge variant={trader.buy_sell_ratio === null || trader.buy_sell_ratio > 1 ? "default" : "outline"}>
+                        {trader.buy_sell_ratio === null ? '∞' : trader.buy_sell_ratio.toFixed(2)}
                       </Badge>
                     </TableCell>
                   </TableRow>
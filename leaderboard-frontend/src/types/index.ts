@@ -1,5 +1,8 @@
 export interface TraderStats {
+  rank?: number | null;
   address: string;
+  label?: string | null;
+  category?: AddressCategory | null;
   total_buys: number;
   total_sells: number;
   total_buy_volume_token: string;
@@ -8,7 +11,20 @@ export interface TraderStats {
   total_sell_volume_usd: string;
   total_volume_usd: string;
   net_volume_token: string;
-  buy_sell_ratio: number;
+  /** Buys per sell; null for traders without sells */
+  buy_sell_ratio: number | null;
+  realized_pnl_usd?: string | null;
+  unrealized_pnl_usd?: string | null;
+  position_token?: string | null;
+  average_entry_price_usd?: string | null;
+  networks?: Record<string, NetworkActivity>;
+  pools?: PoolBreakdown[];
+}
+
+export interface NetworkActivity {
+  total_buys: number;
+  total_sells: number;
+  total_volume_usd: string;
 }
 
 export interface LeaderboardData {
@@ -20,6 +36,22 @@ export interface LeaderboardData {
     total_sell_transactions: number;
     average_volume_per_trader: string;
   };
+  page?: PageInfo;
+  window?: SwapWindow;
+}
+
+export interface PageInfo {
+  offset: number;
+  limit: number;
+  next_offset: number | null;
+  next_cursor: string | null;
+}
+
+export interface SwapWindow {
+  start_block: number | null;
+  end_block: number | null;
+  from_timestamp: number | null;
+  to_timestamp: number | null;
 }
 
 export interface TokenInfo {
@@ -33,7 +65,155 @@ export interface LeaderboardParams {
   token_address?: string;
   start_block?: number;
   end_block?: number;
+  from_timestamp?: number;
+  to_timestamp?: number;
   limit?: number;
+  offset?: number;
+  cursor?: string;
+  attribution?: AttributionMode;
+  attribution_fallback?: AttributionMode;
+  pnl_mode?: CostBasisMode;
+  sort_by?: SortKey;
+  order?: SortOrder;
+  exclude_categories?: AddressCategory[];
   demo?: boolean;
   network?: string;
+  networks?: string[];
+  pools?: string[];
+  fee_tiers?: number[];
+  pool_breakdown?: boolean;
+}
+
+export type AttributionMode = 'origin' | 'recipient' | 'sender';
+
+export type AddressCategory = 'router' | 'aggregator' | 'mev_bot' | 'cex' | 'other';
+
+export type CostBasisMode = 'fifo' | 'average';
+
+export type SortKey =
+  | 'volume'
+  | 'buy_volume'
+  | 'sell_volume'
+  | 'net_flow'
+  | 'trades'
+  | 'buy_sell_ratio'
+  | 'pnl';
+
+export type SortOrder = 'desc' | 'asc';
+
+export interface PoolBreakdown {
+  pool: string;
+  pair: string;
+  fee_tier: number | null;
+  total_buys: number;
+  total_sells: number;
+  buy_volume_usd: string;
+  sell_volume_usd: string;
+  total_volume_usd: string;
+}
+
+export type PoolSortKey = 'volume' | 'swaps' | 'traders' | 'tvl';
+
+export interface PoolStats {
+  rank: number | null;
+  pool: string;
+  pair: string;
+  fee_tier: number | null;
+  swap_count: number;
+  total_buys: number;
+  total_sells: number;
+  buy_volume_usd: string;
+  sell_volume_usd: string;
+  total_volume_usd: string;
+  unique_traders: number;
+  last_price: number | null;
+  quote_symbol: string;
+  tick: number | null;
+  tvl_usd: string | null;
+}
+
+export interface PoolLeaderboardResponse {
+  pools: PoolStats[];
+  total_pools: number;
+  window: SwapWindow;
+}
+
+export interface TraderSwap {
+  id: string;
+  block_number: number;
+  timestamp: number;
+  pool: string;
+  pair: string;
+  side: 'buy' | 'sell';
+  amount_token: string;
+  amount_usd: string;
+}
+
+export interface TraderDetailResponse {
+  trader: TraderStats;
+  pools: PoolBreakdown[];
+  swaps: TraderSwap[];
+  total_swaps: number;
+  offset: number;
+  limit: number;
+  window: SwapWindow;
+}
+
+export interface RankedTrader {
+  rank: number;
+  address: string;
+  total_volume_usd: string;
+  total_buys: number;
+  total_sells: number;
+}
+
+export interface LiveSnapshot {
+  network: string;
+  token_address: string;
+  refreshed_at: number;
+  traders: RankedTrader[];
+}
+
+export interface LiveSwap {
+  id: string;
+  timestamp: string;
+  trader: string;
+  is_buy: boolean;
+  amount_token: string;
+  amount_usd: string;
+}
+
+export interface RankChange {
+  address: string;
+  previous_rank: number | null;
+  rank: number | null;
+  total_volume_usd: string;
+}
+
+export interface LiveUpdate {
+  network: string;
+  token_address: string;
+  refreshed_at: number;
+  new_swaps: LiveSwap[];
+  rank_changes: RankChange[];
+}
+
+export interface ApiError {
+  code:
+    | 'invalid_request'
+    | 'invalid_address'
+    | 'unauthorized'
+    | 'unsupported_network'
+    | 'not_found'
+    | 'upstream_auth'
+    | 'rate_limited'
+    | 'upstream_unavailable'
+    | 'upstream_timeout'
+    | 'upstream_parse_error'
+    | 'upstream_error'
+    | 'source_unavailable'
+    | 'internal';
+  message: string;
+  details?: string | null;
+  retryable: boolean;
 }
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use ethers::utils::{hex, keccak256};
+use tokio::sync::OnceCell;
+
+/// Strong ETag (quoted) derived from a response body.
+pub fn etag(body: &[u8]) -> String {
+    format!("\"{}\"", hex::encode(&keccak256(body)[..16]))
+}
+
+/// A cached value and when it was computed.
+#[derive(Debug)]
+pub struct Cached<T> {
+    pub value: T,
+    cached_at: Instant,
+}
+
+impl<T> Cached<T> {
+    fn new(value: T) -> Self {
+        Self {
+            value,
+            cached_at: Instant::now(),
+        }
+    }
+
+    fn remaining(&self, ttl: Duration) -> Duration {
+        ttl.saturating_sub(self.cached_at.elapsed())
+    }
+}
+
+type Slot<T> = Arc<OnceCell<Arc<Cached<T>>>>;
+
+/// In-process response cache with a fixed TTL. Concurrent requests for the
+/// same key share one computation; failed computations are not cached, so
+/// the next caller simply tries again.
+pub struct ResponseCache<T> {
+    ttl: Duration,
+    slots: Mutex<HashMap<String, Slot<T>>>,
+}
+
+impl<T> ResponseCache<T> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Time left before `entry` expires from this cache
+    pub fn ttl_remaining(&self, entry: &Cached<T>) -> Duration {
+        entry.remaining(self.ttl)
+    }
+
+    pub async fn get_or_try_insert<F, Fut, E>(
+        &self,
+        key: String,
+        compute: F,
+    ) -> Result<Arc<Cached<T>>, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let slot = self.slot(key.clone());
+        let result = slot
+            .get_or_try_init(|| async { compute().await.map(|value| Arc::new(Cached::new(value))) })
+            .await
+            .cloned();
+
+        // A failed computation leaves its slot empty; drop it so failing keys
+        // don't pile up, unless a newer slot or a concurrent retry replaced it
+        if result.is_err() && !slot.initialized() {
+            let mut slots = self.slots();
+            if slots
+                .get(&key)
+                .is_some_and(|stored| Arc::ptr_eq(stored, &slot))
+            {
+                slots.remove(&key);
+            }
+        }
+        result
+    }
+
+    /// Returns the live slot for `key`, replacing it if its value has expired
+    /// and dropping other expired entries along the way. Empty slots that no
+    /// request is computing count as expired.
+    fn slot(&self, key: String) -> Slot<T> {
+        let mut slots = self.slots();
+        let ttl = self.ttl;
+        let expired = |slot: &Slot<T>| match slot.get() {
+            Some(entry) => entry.remaining(ttl).is_zero(),
+            None => Arc::strong_count(slot) == 1,
+        };
+
+        slots.retain(|_, slot| !expired(slot));
+        slots.entry(key).or_default().clone()
+    }
+
+    fn slots(&self) -> MutexGuard<'_, HashMap<String, Slot<T>>> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_concurrent_requests_share_one_computation() {
+        let cache = Arc::new(ResponseCache::new(Duration::from_secs(60)));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let requests = (0..8).map(|_| {
+            let cache = cache.clone();
+            let calls = calls.clone();
+            tokio::spawn(async move {
+                cache
+                    .get_or_try_insert("key".to_string(), || async move {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        Ok::<_, ()>(b"{}".to_vec())
+                    })
+                    .await
+                    .unwrap()
+            })
+        });
+        let entries: Vec<Arc<Cached<Vec<u8>>>> = join_all(requests).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(entries.windows(2).all(|w| Arc::ptr_eq(&w[0], &w[1])));
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_not_cached_and_entries_expire() {
+        let cache = ResponseCache::new(Duration::from_millis(30));
+        let failed = cache
+            .get_or_try_insert("key".to_string(), || async { Err::<Vec<u8>, _>("down") })
+            .await;
+        assert!(failed.is_err());
+
+        let first = cache
+            .get_or_try_insert("key".to_string(), || async { Ok::<_, ()>(b"1".to_vec()) })
+            .await
+            .unwrap();
+        assert_eq!(first.value, b"1");
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        let refreshed = cache
+            .get_or_try_insert("key".to_string(), || async { Ok::<_, ()>(b"2".to_vec()) })
+            .await
+            .unwrap();
+        assert_eq!(refreshed.value, b"2");
+        assert_ne!(etag(&first.value), etag(&refreshed.value));
+    }
+
+    #[tokio::test]
+    async fn test_failed_keys_are_removed() {
+        let cache = ResponseCache::<Vec<u8>>::new(Duration::from_secs(60));
+        for token in ["0xbad", "0xworse"] {
+            let failed = cache
+                .get_or_try_insert(token.to_string(), || async { Err::<Vec<u8>, _>("down") })
+                .await;
+            assert!(failed.is_err());
+            assert!(!cache.slots().contains_key(token));
+        }
+        assert!(cache.slots().is_empty());
+    }
+
+    async fn join_all<T: Send + 'static>(
+        handles: impl Iterator<Item = tokio::task::JoinHandle<T>>,
+    ) -> Vec<T> {
+        let mut results = Vec::new();
+        for handle in handles.collect::<Vec<_>>() {
+            results.push(handle.await.unwrap());
+        }
+        results
+    }
+}
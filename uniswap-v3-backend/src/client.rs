@@ -1,9 +1,119 @@
 use anyhow::{Result, anyhow};
-use reqwest::Client;
-use std::env;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::time::Duration;
+use thiserror::Error;
 
-use crate::config::{Config, NetworkConfig};
-use crate::types::{GraphQLQuery, GraphQLResponse, Swap, SwapsResponse};
+use crate::config::Config;
+use crate::sources::SwapSource;
+use crate::types::{
+    GraphQLError, GraphQLQuery, GraphQLResponse, MetaResponse, PoolFilter, Swap,
+    SwapBlocksResponse, SwapWindow, SwapsResponse,
+};
+use crate::utils::{format_timestamp, is_valid_address};
+
+/// Failures talking to the subgraph, classified so callers can react without
+/// inspecting message text.
+#[derive(Debug, Error)]
+pub enum ClientError {
+    /// The request was rejected before being sent, e.g. a malformed address
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    /// The request never produced an HTTP response (DNS, connect, timeout)
+    #[error("Request to the subgraph failed: {0}")]
+    Transport(#[from] reqwest::Error),
+    #[error("HTTP error {status}: {body}")]
+    Http {
+        status: u16,
+        body: String,
+        /// Delay requested by the server's `Retry-After` header
+        retry_after: Option<Duration>,
+    },
+    /// The gateway rejected the API key
+    #[error("Authentication error: The Graph Network endpoint authentication failed: {0}")]
+    Auth(String),
+    /// The subgraph id is unknown to the gateway
+    #[error("Subgraph not found: {0}")]
+    SubgraphNotFound(String),
+    /// No indexer could serve the query right now
+    #[error("Indexer unavailable: {0}")]
+    IndexerUnavailable(String),
+    #[error("GraphQL errors: {}", .0.join(", "))]
+    GraphQl(Vec<String>),
+    /// The response body could not be decoded into the expected shape
+    #[error("Failed to decode subgraph response: {0}")]
+    Decode(String),
+}
+
+impl ClientError {
+    /// Whether the same request may succeed if sent again later
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport(e) => e.is_timeout() || e.is_connect() || e.is_request(),
+            Self::Http { status, .. } => *status == 429 || (500..=599).contains(status),
+            Self::IndexerUnavailable(_) => true,
+            _ => false,
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Http { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Classifies the `errors` array of a GraphQL response by each error's
+    /// `extensions.code` or, since the gateway mostly reports only message
+    /// text, by its known message prefixes. Anything else is a plain,
+    /// non-retryable `GraphQl` error.
+    fn from_graphql(errors: Vec<GraphQLError>) -> Self {
+        let messages: Vec<String> = errors.iter().map(|e| e.message.clone()).collect();
+        let combined = messages.join(", ");
+        errors
+            .iter()
+            .find_map(|error| {
+                let code = error.extensions.as_ref().and_then(|e| e.code.as_deref());
+                let message = error.message.trim_start();
+                let kind: fn(String) -> Self = match code {
+                    Some("UNAUTHENTICATED" | "FORBIDDEN") => Self::Auth,
+                    // Prefixes of The Graph gateway's error messages
+                    _ if message.starts_with("auth error:") => Self::Auth,
+                    _ if message.starts_with("subgraph not found:") => Self::SubgraphNotFound,
+                    _ if message.starts_with("no indexers found")
+                        || message.starts_with("bad indexers:") =>
+                    {
+                        Self::IndexerUnavailable
+                    }
+                    _ => return None,
+                };
+                Some(kind(combined.clone()))
+            })
+            .unwrap_or(Self::GraphQl(messages))
+    }
+}
+
+/// Keyset position of the last swap returned by the subgraph. Pages are ordered
+/// by `(timestamp, id)` descending, so the next page starts strictly after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapCursor {
+    pub timestamp: String,
+    pub id: String,
+}
+
+impl SwapCursor {
+    pub fn after(swap: &Swap) -> Self {
+        Self {
+            timestamp: swap.timestamp.clone(),
+            id: swap.id.clone(),
+        }
+    }
+}
 
 pub struct UniswapClient {
     client: Client,
@@ -13,130 +123,257 @@ pub struct UniswapClient {
 }
 
 impl UniswapClient {
-    pub fn new(network: &str) -> Result<Self> {
-        let network_config = NetworkConfig::get(network)?;
-        let config = Config::from_env()?;
-
-        let subgraph_url = env::var("UNISWAP_SUBGRAPH_URL").unwrap_or_else(|_| {
-            format!(
-                "https://gateway.thegraph.com/api/{}/subgraphs/id/{}",
-                config.graph_api_key, network_config.subgraph_id
-            )
-        });
+    pub fn new(network: &str, config: &Config) -> Result<Self> {
+        let network_config = config.networks.get(network)?;
+
+        // UNISWAP_SUBGRAPH_URL overrides the registry for every network
+        let subgraph_url = match (
+            &config.subgraph_url,
+            &network_config.subgraph_url,
+            &network_config.subgraph_id,
+        ) {
+            (Some(url), _, _) | (None, Some(url), _) => url.clone(),
+            (None, None, Some(id)) => {
+                let api_key = config.graph_api_key.as_deref().ok_or_else(|| {
+                    anyhow!(
+                        "GRAPH_API_KEY is not set; it is needed to query the {} subgraph on The Graph's gateway",
+                        network_config.id
+                    )
+                })?;
+                format!(
+                    "https://gateway.thegraph.com/api/{}/subgraphs/id/{}",
+                    api_key, id
+                )
+            }
+            (None, None, None) => {
+                return Err(anyhow!(
+                    "Network {} has no subgraph_url or subgraph_id; set one in NETWORKS_PATH or use another swap source",
+                    network_config.id
+                ));
+            }
+        };
 
-        println!("Using {} network ({})", network_config.name, network);
+        eprintln!("Using {} network ({})", network_config.name, network);
+
+        let client = Client::builder().timeout(config.request_timeout).build()?;
 
         Ok(Self {
-            client: Client::new(),
+            client,
             subgraph_url,
             network: network.to_string(),
-            config,
+            config: config.clone(),
         })
     }
 
     pub async fn fetch_swaps(
         &self,
         token_address: &str,
-        start_block: Option<u64>,
-        end_block: Option<u64>,
-        skip: usize,
+        window: &SwapWindow,
+        pools: &PoolFilter,
+        cursor: Option<&SwapCursor>,
         first: usize,
-    ) -> Result<Vec<Swap>> {
+    ) -> Result<Vec<Swap>, ClientError> {
         // Validate token address format
         let token_lower = token_address.to_lowercase();
-        if !token_lower.starts_with("0x") || token_lower.len() != 42 {
-            return Err(anyhow!(
+        if !is_valid_address(&token_lower) {
+            return Err(ClientError::InvalidInput(
                 "Invalid token address format. Expected 42-character hex string starting with '0x'"
+                    .to_string(),
             ));
         }
 
-        // Check if it's a valid hex string
-        if !token_lower[2..].chars().all(|c| c.is_ascii_hexdigit()) {
-            return Err(anyhow!(
-                "Invalid token address. Contains non-hexadecimal characters."
-            ));
-        }
-
-        // Query with token filtering to get swaps for specific token
-        let query = format!(
-            r#"
-            query GetSwaps($skip: Int!, $first: Int!) {{
+        // Token, pool, block and time filtering happens in the subgraph so that
+        // every page we fetch is already inside the requested window. The Graph breaks
+        // timestamp ties on `id` in the same direction, which the cursor relies on.
+        let query = r#"
+            query GetSwaps($first: Int!, $where: Swap_filter!) {
                 swaps(
-                    skip: $skip,
                     first: $first,
                     orderBy: timestamp,
                     orderDirection: desc,
-                    where: {{
-                        or: [
-                            {{ pool_: {{ token0: "{}" }} }},
-                            {{ pool_: {{ token1: "{}" }} }}
-                        ]
-                    }}
-                ) {{
+                    where: $where
+                ) {
                     id
                     timestamp
                     sender
                     recipient
+                    origin
                     amount0
                     amount1
                     amountUSD
-                    pool {{
+                    logIndex
+                    pool {
                         id
-                        token0 {{
+                        feeTier
+                        token0 {
                             id
                             symbol
                             name
                             decimals
-                        }}
-                        token1 {{
+                        }
+                        token1 {
                             id
                             symbol
                             name
                             decimals
-                        }}
+                        }
                         tick
                         sqrtPrice
-                    }}
-                    transaction {{
+                        totalValueLockedUSD
+                    }
+                    transaction {
                         blockNumber
-                    }}
-                }}
-            }}
-            "#,
-            token_lower, token_lower
-        );
+                    }
+                }
+            }
+            "#
+        .to_string();
 
         let variables = serde_json::json!({
-            "skip": skip,
-            "first": first
+            "first": first,
+            "where": swap_filter(&token_lower, window, pools, cursor),
         });
 
+        let response: Option<SwapsResponse> = self.execute_query(query, variables).await?;
+        Ok(response.map(|data| data.swaps).unwrap_or_default())
+    }
+
+    /// Latest block the subgraph has indexed, which is the effective chain head
+    /// for every query we can make against it.
+    pub async fn fetch_latest_block(&self) -> Result<u64, ClientError> {
+        let query = r#"
+            query GetLatestBlock {
+                _meta {
+                    block {
+                        number
+                    }
+                }
+            }
+            "#
+        .to_string();
+
+        let response: Option<MetaResponse> =
+            self.execute_query(query, serde_json::json!({})).await?;
+
+        response.map(|data| data.meta.block.number).ok_or_else(|| {
+            ClientError::Decode("Subgraph did not report its latest indexed block".to_string())
+        })
+    }
+
+    /// Block of the earliest swap at or after `timestamp` in any pool. No
+    /// earlier block holds a swap that late, so this bounds a time window as a
+    /// block range. `None` when nothing has been swapped since.
+    ///
+    /// Ordered by block rather than timestamp: on fast chains several blocks
+    /// share a second, and a timestamp tie could return a later one.
+    pub async fn fetch_first_block_at(&self, timestamp: u64) -> Result<Option<u64>, ClientError> {
+        let query = r#"
+            query GetFirstBlockAt($timestamp: BigInt!) {
+                swaps(
+                    first: 1,
+                    orderBy: transaction__blockNumber,
+                    orderDirection: asc,
+                    where: { timestamp_gte: $timestamp }
+                ) {
+                    transaction {
+                        blockNumber
+                    }
+                }
+            }
+            "#
+        .to_string();
+
+        let response: Option<SwapBlocksResponse> = self
+            .execute_query(
+                query,
+                serde_json::json!({ "timestamp": timestamp.to_string() }),
+            )
+            .await?;
+        let Some(swap) = response.and_then(|data| data.swaps.into_iter().next()) else {
+            return Ok(None);
+        };
+        let block = &swap.transaction.block_number;
+        block
+            .parse()
+            .map(Some)
+            .map_err(|_| ClientError::Decode(format!("Invalid block number: {}", block)))
+    }
+
+    /// Sends a query, retrying transient failures with jittered exponential
+    /// backoff (or the server's `Retry-After`) up to `MAX_RETRIES` times.
+    /// Pagination calls this per page, so a retry resumes from the page that
+    /// failed rather than restarting the whole fetch.
+    async fn execute_query<T: DeserializeOwned>(
+        &self,
+        query: String,
+        variables: Value,
+    ) -> Result<Option<T>, ClientError> {
         let request = GraphQLQuery { query, variables };
+        let mut attempt = 0;
+
+        loop {
+            match self.send_query(&request).await {
+                Err(e) if e.is_retryable() && attempt < self.config.max_retries => {
+                    let delay = e.retry_after().unwrap_or_else(|| {
+                        backoff_delay(
+                            self.config.retry_base_delay,
+                            attempt,
+                            &mut rand::thread_rng(),
+                        )
+                    });
+                    attempt += 1;
+                    eprintln!(
+                        "⚠️  {} (retry {}/{} in {:.1}s)",
+                        e,
+                        attempt,
+                        self.config.max_retries,
+                        delay.as_secs_f64()
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
 
+    async fn send_query<T: DeserializeOwned>(
+        &self,
+        request: &GraphQLQuery,
+    ) -> Result<Option<T>, ClientError> {
         let response = self
             .client
             .post(&self.subgraph_url)
-            .json(&request)
+            .json(request)
             .send()
             .await?;
 
         // Check if response is successful
         if !response.status().is_success() {
             let status = response.status();
-            let error_text = response
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| parse_retry_after(value, Utc::now()));
+            let body = response
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(anyhow!("HTTP error {}: {}", status, error_text));
+            return Err(match status {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ClientError::Auth(body),
+                _ => ClientError::Http {
+                    status: status.as_u16(),
+                    body,
+                    retry_after,
+                },
+            });
         }
 
         // Get response text first to debug parsing issues
         let response_text = response.text().await?;
 
         // Try to parse as JSON, with better error handling
-        let graphql_response: GraphQLResponse<SwapsResponse> = match serde_json::from_str(
-            &response_text,
-        ) {
+        let graphql_response: GraphQLResponse<T> = match serde_json::from_str(&response_text) {
             Ok(parsed) => parsed,
             Err(parse_err) => {
                 eprintln!("Failed to parse response as JSON: {}", parse_err);
@@ -149,141 +386,393 @@ impl UniswapClient {
                     }
                 );
 
-                // Check if it's an HTML error page
+                // Gateways serve an HTML error page when no indexer can answer
                 if response_text.trim_start().starts_with("<!DOCTYPE html>")
                     || response_text.trim_start().starts_with("<html")
                 {
-                    return Err(anyhow!(
-                        "Received HTML error page instead of JSON. The token address '{}' might not exist or have any pools on Uniswap V3.",
-                        token_address
+                    return Err(ClientError::IndexerUnavailable(
+                        "Received HTML error page instead of JSON".to_string(),
                     ));
                 }
 
-                return Err(anyhow!(
-                    "Failed to parse API response for token '{}': {}",
-                    token_address,
-                    parse_err
-                ));
+                return Err(ClientError::Decode(parse_err.to_string()));
             }
         };
 
         if let Some(errors) = graphql_response.errors {
-            let error_messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
-            let combined_errors = error_messages.join(", ");
-
-            // Provide helpful guidance for common errors
-            if combined_errors.contains("auth") || combined_errors.contains("authorization") {
-                return Err(anyhow!(
-                    "Authentication error: The Graph Network endpoint authentication failed.\n\
-                    \n\
-                    The API key may have expired or have rate limits.\n\
-                    Try demo mode instead: {} --demo --limit 10",
-                    std::env::args()
-                        .next()
-                        .unwrap_or("uni-leaderboard".to_string())
-                ));
-            } else if combined_errors.contains("subgraph not found") {
-                return Err(anyhow!(
-                    "Subgraph not found error: The subgraph endpoint may be incorrect.\n\
-                    \n\
-                    The subgraph ID may be outdated.\n\
-                    Try demo mode instead: {} --demo --limit 10\n\
-                    \n\
-                    Original error: {}",
-                    std::env::args()
-                        .next()
-                        .unwrap_or("uni-leaderboard".to_string()),
-                    combined_errors
-                ));
-            } else {
-                return Err(anyhow!("GraphQL errors: {}", combined_errors));
-            }
+            return Err(ClientError::from_graphql(errors));
         }
 
-        match graphql_response.data {
-            Some(data) => {
-                // Apply block range filtering if specified
-                let filtered_swaps: Vec<Swap> = data
-                    .swaps
-                    .into_iter()
-                    .filter(|swap| {
-                        if let Ok(block_num) = swap.transaction.block_number.parse::<u64>() {
-                            let in_start_range =
-                                start_block.map_or(true, |start| block_num >= start);
-                            let in_end_range = end_block.map_or(true, |end| block_num <= end);
-                            in_start_range && in_end_range
-                        } else {
-                            true // Include if we can't parse block number
-                        }
-                    })
-                    .collect();
-                Ok(filtered_swaps)
-            }
-            None => Ok(vec![]),
-        }
+        Ok(graphql_response.data)
     }
 
     pub async fn fetch_all_swaps(
         &self,
         token_address: &str,
-        _start_block: Option<u64>, // Ignored - we'll get latest swaps
-        _end_block: Option<u64>,   // Ignored - we'll get latest swaps
+        window: &SwapWindow,
+        pools: &PoolFilter,
     ) -> Result<Vec<Swap>> {
         let mut all_swaps = Vec::new();
-        let mut skip = 0;
+        let mut seen_ids = HashSet::new();
+        let mut cursor: Option<SwapCursor> = None;
 
-        println!("Fetching latest swap data from Uniswap v3 subgraph...");
-        println!("Network: {}", self.network);
-        println!("Looking for token: {}", token_address);
-        println!("Target: {} latest swaps", self.config.target_swaps);
+        eprintln!("Fetching swap data from Uniswap v3 subgraph...");
+        eprintln!("Network: {}", self.network);
+        eprintln!("Looking for token: {}", token_address);
+        eprintln!(
+            "Block range: {} to {}",
+            window
+                .start_block
+                .map_or("genesis".to_string(), |b| b.to_string()),
+            window
+                .end_block
+                .map_or("latest".to_string(), |b| b.to_string())
+        );
+        if window.has_time_bounds() {
+            eprintln!(
+                "Time range: {} to {}",
+                window
+                    .from_timestamp
+                    .map_or("earliest".to_string(), format_timestamp),
+                window
+                    .to_timestamp
+                    .map_or("latest".to_string(), format_timestamp)
+            );
+        }
+        match self.config.target_swaps {
+            Some(target) => eprintln!("Target: up to {} swaps", target),
+            None => eprintln!("Target: every swap in the requested range"),
+        }
 
         loop {
-            let swaps = self
-                .fetch_swaps(token_address, None, None, skip, self.config.batch_size)
-                .await?;
+            // Each page is retried on its own (see `execute_query`), so a
+            // transient failure resumes from `cursor` instead of page one
+            let swaps = match self
+                .fetch_swaps(
+                    token_address,
+                    window,
+                    pools,
+                    cursor.as_ref(),
+                    self.config.batch_size,
+                )
+                .await
+            {
+                Ok(swaps) => swaps,
+                Err(e) => {
+                    eprintln!("Giving up after fetching {} swaps: {}", all_swaps.len(), e);
+                    return Err(e.into());
+                }
+            };
 
             if swaps.is_empty() {
                 if all_swaps.is_empty() {
-                    println!(
+                    eprintln!(
                         "No swaps found for token {}. This could mean:",
                         token_address
                     );
-                    println!("  • Token has no recent trading activity");
-                    println!(
+                    eprintln!("  • Token has no trading activity in the requested range");
+                    eprintln!(
                         "  • Token address is incorrect or doesn't exist on {}",
                         self.network
                     );
-                    println!("  • Token is not traded on Uniswap v3 on {}", self.network);
-                    println!(
+                    eprintln!("  • Token is not traded on Uniswap v3 on {}", self.network);
+                    eprintln!(
                         "  • Try switching networks (use --network arbitrum/ethereum/polygon)"
                     );
-                    println!("  • Try with a more active token");
+                    eprintln!("  • Try with a more active token");
                 }
                 break;
             }
 
-            println!(
-                "Fetched {} swaps (total: {})",
-                swaps.len(),
-                all_swaps.len() + swaps.len()
+            let batch_len = swaps.len();
+            cursor = swaps.last().map(SwapCursor::after);
+
+            // The cursor already excludes everything we've seen, but guard
+            // against the indexer returning overlapping pages during reorgs
+            all_swaps.extend(
+                swaps
+                    .into_iter()
+                    .filter(|swap| seen_ids.insert(swap.id.clone())),
             );
 
-            let batch_len = swaps.len();
-            all_swaps.extend(swaps);
+            eprintln!("Fetched {} swaps (total: {})", batch_len, all_swaps.len());
 
-            // Stop if we hit our target or got less than a full batch
-            if all_swaps.len() >= self.config.target_swaps || batch_len < self.config.batch_size {
+            // A short batch means the requested window is exhausted
+            if batch_len < self.config.batch_size {
                 break;
             }
 
-            skip += self.config.batch_size;
+            if let Some(target) = self.config.target_swaps
+                && all_swaps.len() >= target
+            {
+                eprintln!(
+                    "⚠️  Reached TARGET_SWAPS ({}) before the end of the requested range; older swaps in the window were not fetched",
+                    target
+                );
+                all_swaps.truncate(target);
+                break;
+            }
         }
 
-        println!(
-            "Total swaps fetched: {} (latest swaps from {} network)",
+        eprintln!(
+            "Total swaps fetched: {} (from {} network)",
             all_swaps.len(),
             self.network
         );
         Ok(all_swaps)
     }
 }
+
+#[async_trait]
+impl SwapSource for UniswapClient {
+    fn name(&self) -> &'static str {
+        "subgraph"
+    }
+
+    async fn latest_block(&self) -> Result<u64> {
+        Ok(self.fetch_latest_block().await?)
+    }
+
+    async fn first_block_at(&self, timestamp: u64) -> Result<u64> {
+        match self.fetch_first_block_at(timestamp).await? {
+            Some(block) => Ok(block),
+            None => Ok(self.fetch_latest_block().await? + 1),
+        }
+    }
+
+    async fn fetch_swaps(
+        &self,
+        token_address: &str,
+        window: &SwapWindow,
+        pools: &PoolFilter,
+    ) -> Result<Vec<Swap>> {
+        self.fetch_all_swaps(token_address, window, pools).await
+    }
+}
+
+/// Longest delay between retries, whether computed or requested by the server
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Exponential backoff for retry `attempt` (0-based) with "equal jitter": the
+/// delay is drawn from the upper half of `base * 2^attempt`, so concurrent
+/// clients spread out without ever retrying immediately.
+fn backoff_delay(base: Duration, attempt: u32, rng: &mut impl Rng) -> Duration {
+    let ceiling = base
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_RETRY_DELAY);
+    let half = ceiling / 2;
+    half + half.mul_f64(rng.r#gen::<f64>())
+}
+
+/// Parses a `Retry-After` header given either as delay seconds or an HTTP date.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let delay = match value.trim().parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => {
+            let at = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+            (at.with_timezone(&Utc) - now).to_std().unwrap_or_default()
+        }
+    };
+    Some(delay.min(MAX_RETRY_DELAY))
+}
+
+/// Builds the subgraph `where` filter selecting swaps in any pool that contains
+/// `token`, optionally narrowed to the pools and fee tiers in `pools`, bounded by
+/// the block and timestamp limits in `window` and positioned after `cursor` in
+/// `(timestamp, id)` descending order.
+fn swap_filter(
+    token: &str,
+    window: &SwapWindow,
+    pools: &PoolFilter,
+    cursor: Option<&SwapCursor>,
+) -> Value {
+    let mut clauses = vec![serde_json::json!({
+        "or": [
+            { "pool_": { "token0": token } },
+            { "pool_": { "token1": token } }
+        ]
+    })];
+
+    if !pools.pools.is_empty() {
+        clauses.push(serde_json::json!({ "pool_in": pools.pools }));
+    }
+    if !pools.fee_tiers.is_empty() {
+        // feeTier is a BigInt, which the subgraph takes as a string
+        let fee_tiers: Vec<String> = pools.fee_tiers.iter().map(u32::to_string).collect();
+        clauses.push(serde_json::json!({ "pool_": { "feeTier_in": fee_tiers } }));
+    }
+
+    let mut block_filter = serde_json::Map::new();
+    if let Some(start) = window.start_block {
+        block_filter.insert("blockNumber_gte".to_string(), start.to_string().into());
+    }
+    if let Some(end) = window.end_block {
+        block_filter.insert("blockNumber_lte".to_string(), end.to_string().into());
+    }
+    if !block_filter.is_empty() {
+        clauses.push(serde_json::json!({ "transaction_": block_filter }));
+    }
+
+    let mut time_filter = serde_json::Map::new();
+    if let Some(from) = window.from_timestamp {
+        time_filter.insert("timestamp_gte".to_string(), from.to_string().into());
+    }
+    if let Some(to) = window.to_timestamp {
+        time_filter.insert("timestamp_lte".to_string(), to.to_string().into());
+    }
+    if !time_filter.is_empty() {
+        clauses.push(Value::Object(time_filter));
+    }
+
+    if let Some(cursor) = cursor {
+        clauses.push(serde_json::json!({
+            "or": [
+                { "timestamp_lt": cursor.timestamp },
+                { "timestamp": cursor.timestamp, "id_lt": cursor.id }
+            ]
+        }));
+    }
+
+    if clauses.len() == 1 {
+        return clauses.remove(0);
+    }
+
+    serde_json::json!({ "and": clauses })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+    #[test]
+    fn test_graphql_errors_are_classified_by_prefix_or_code() {
+        let classify = |message: &str, code: Option<&str>| {
+            ClientError::from_graphql(vec![GraphQLError {
+                message: message.to_string(),
+                extensions: code.map(|code| crate::types::GraphQLErrorExtensions {
+                    code: Some(code.to_string()),
+                }),
+            }])
+        };
+
+        let error = classify("Field `author` is not defined on type `Swap`", None);
+        assert!(matches!(error, ClientError::GraphQl(_)));
+        assert!(!error.is_retryable());
+        let error = classify("Store error: service temporarily unavailable", None);
+        assert!(matches!(error, ClientError::GraphQl(_)));
+
+        assert!(matches!(
+            classify("auth error: malformed API key", None),
+            ClientError::Auth(_)
+        ));
+        assert!(matches!(
+            classify("subgraph not found: 5zvR82", None),
+            ClientError::SubgraphNotFound(_)
+        ));
+        assert!(classify("bad indexers: {0xabc: Timeout}", None).is_retryable());
+        assert!(matches!(
+            classify("Invalid token", Some("UNAUTHENTICATED")),
+            ClientError::Auth(_)
+        ));
+    }
+
+    #[test]
+    fn test_backoff_delay_grows_with_jitter_and_caps() {
+        let base = Duration::from_millis(500);
+        let mut rng = rand::thread_rng();
+        for attempt in 0..4 {
+            let ceiling = base * 2u32.pow(attempt);
+            let delay = backoff_delay(base, attempt, &mut rng);
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "{:?}", delay);
+        }
+        assert!(backoff_delay(base, 30, &mut rng) <= MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_parse_retry_after_seconds_and_http_date() {
+        let now = DateTime::parse_from_rfc3339("2024-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(parse_retry_after("7", now), Some(Duration::from_secs(7)));
+        assert_eq!(
+            parse_retry_after("Sat, 01 Jun 2024 12:00:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(parse_retry_after("3600", now), Some(MAX_RETRY_DELAY));
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_swap_filter_without_range() {
+        let filter = swap_filter(TOKEN, &SwapWindow::default(), &PoolFilter::default(), None);
+        assert_eq!(filter["or"][0]["pool_"]["token0"], TOKEN);
+        assert_eq!(filter["or"][1]["pool_"]["token1"], TOKEN);
+        assert!(filter.get("and").is_none());
+    }
+
+    #[test]
+    fn test_swap_filter_with_block_range() {
+        let window = SwapWindow {
+            start_block: Some(100),
+            end_block: Some(200),
+            ..Default::default()
+        };
+        let filter = swap_filter(TOKEN, &window, &PoolFilter::default(), None);
+        let block_filter = &filter["and"][1]["transaction_"];
+        assert_eq!(block_filter["blockNumber_gte"], "100");
+        assert_eq!(block_filter["blockNumber_lte"], "200");
+
+        let window = SwapWindow {
+            start_block: Some(100),
+            ..Default::default()
+        };
+        let open_ended = swap_filter(TOKEN, &window, &PoolFilter::default(), None);
+        let block_filter = &open_ended["and"][1]["transaction_"];
+        assert_eq!(block_filter["blockNumber_gte"], "100");
+        assert!(block_filter.get("blockNumber_lte").is_none());
+    }
+
+    #[test]
+    fn test_swap_filter_with_time_range() {
+        let window = SwapWindow {
+            from_timestamp: Some(1_714_521_600),
+            to_timestamp: Some(1_717_199_999),
+            ..Default::default()
+        };
+        let filter = swap_filter(TOKEN, &window, &PoolFilter::default(), None);
+        assert_eq!(filter["and"][1]["timestamp_gte"], "1714521600");
+        assert_eq!(filter["and"][1]["timestamp_lte"], "1717199999");
+    }
+
+    #[test]
+    fn test_swap_filter_with_pools() {
+        let pool = "0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640";
+        let pools = PoolFilter::new(&[pool.to_string()], &[500, 3000]);
+        let filter = swap_filter(TOKEN, &SwapWindow::default(), &pools, None);
+        assert_eq!(filter["and"][1]["pool_in"][0], pool.to_lowercase());
+        assert_eq!(
+            filter["and"][2]["pool_"]["feeTier_in"],
+            serde_json::json!(["500", "3000"])
+        );
+    }
+
+    #[test]
+    fn test_swap_filter_with_cursor() {
+        let cursor = SwapCursor {
+            timestamp: "1700000000".to_string(),
+            id: "0xabc#12".to_string(),
+        };
+        let filter = swap_filter(
+            TOKEN,
+            &SwapWindow::default(),
+            &PoolFilter::default(),
+            Some(&cursor),
+        );
+        let keyset = &filter["and"][1]["or"];
+        assert_eq!(keyset[0]["timestamp_lt"], "1700000000");
+        assert_eq!(keyset[1]["timestamp"], "1700000000");
+        assert_eq!(keyset[1]["id_lt"], "0xabc#12");
+    }
+}
@@ -1,79 +1,471 @@
 use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
 use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::labels::LabelRegistry;
+use crate::networks::NetworkRegistry;
+use crate::refresher::WatchedToken;
+use crate::sources::SourceKind;
+use crate::tokens::TokenMap;
+use crate::utils::is_valid_address;
+
+/// Config file picked up from the working directory when none is named
+const DEFAULT_CONFIG_FILE: &str = "leaderboard.toml";
+
+/// Every setting as `(config file key, environment variable)`.
+const SETTINGS: &[(&str, &str)] = &[
+    ("graph_api_key", "GRAPH_API_KEY"),
+    ("subgraph_url", "UNISWAP_SUBGRAPH_URL"),
+    ("default_port", "DEFAULT_PORT"),
+    ("default_limit", "DEFAULT_LIMIT"),
+    ("target_swaps", "TARGET_SWAPS"),
+    ("batch_size", "BATCH_SIZE"),
+    ("max_retries", "MAX_RETRIES"),
+    ("retry_base_delay_ms", "RETRY_BASE_DELAY_MS"),
+    ("request_timeout_secs", "REQUEST_TIMEOUT_SECS"),
+    ("cache_ttl_secs", "CACHE_TTL_SECS"),
+    ("watchlist", "WATCHLIST"),
+    ("refresh_interval_secs", "REFRESH_INTERVAL_SECS"),
+    ("admin_token", "ADMIN_TOKEN"),
+    ("swap_source", "SWAP_SOURCE"),
+    ("rpc_url", "RPC_URL"),
+    ("factory_address", "UNISWAP_V3_FACTORY"),
+    ("rpc_log_range", "RPC_LOG_RANGE"),
+    ("fixture_path", "SWAP_FIXTURE_PATH"),
+    ("labels_path", "ADDRESS_LABELS_PATH"),
+    ("store_path", "SWAP_STORE_PATH"),
+    ("token_map_path", "TOKEN_MAP_PATH"),
+    ("networks_path", "NETWORKS_PATH"),
+];
 
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub graph_api_key: String,
+    /// The Graph gateway key; only needed for networks queried by `subgraph_id`
+    pub graph_api_key: Option<String>,
+    /// Subgraph endpoint used for every network instead of the registry's
+    pub subgraph_url: Option<String>,
     pub default_port: u16,
     pub default_limit: usize,
-    pub target_swaps: usize,
+    /// Upper bound on swaps fetched per request; `None` fetches the whole range
+    pub target_swaps: Option<usize>,
     pub batch_size: usize,
+    /// Retries after a transient subgraph failure (timeouts, 429, 5xx)
+    pub max_retries: u32,
+    /// First backoff delay; doubles per retry, with jitter
+    pub retry_base_delay: Duration,
+    pub request_timeout: Duration,
+    /// How long identical `/api/leaderboard` responses are served from memory
+    pub cache_ttl: Duration,
+    /// Tokens the server keeps warm in the background (`WATCHLIST`)
+    pub watchlist: Vec<WatchedToken>,
+    pub refresh_interval: Duration,
+    /// Bearer token required by the watchlist admin endpoints
+    pub admin_token: Option<String>,
+    pub swap_source: SourceKind,
+    pub rpc_url: Option<String>,
+    /// Overrides the network's Uniswap v3 factory, e.g. for a local deployment
+    pub factory_address: Option<String>,
+    /// Widest block span requested per `eth_getLogs` call
+    pub rpc_log_range: u64,
+    pub fixture_path: Option<String>,
+    /// User label registry (TOML or JSON) merged over the bundled labels
+    pub labels_path: Option<String>,
+    /// SQLite file swaps are persisted to between runs; `None` disables the store
+    pub store_path: Option<String>,
+    /// User token map (TOML or JSON) merged over the bundled per-network addresses
+    pub token_map_path: Option<String>,
+    /// Bundled networks plus any added or overridden in `NETWORKS_PATH`
+    pub networks: NetworkRegistry,
+    /// Config file the settings were read from, if any
+    pub config_path: Option<String>,
+    /// Profile of the config file applied over its top-level settings
+    pub profile: Option<String>,
+}
+
+/// Where settings come from besides the environment.
+#[derive(Debug, Default)]
+pub struct ConfigSources {
+    /// Config file (defaults to `LEADERBOARD_CONFIG`, then `./leaderboard.toml` if present)
+    pub path: Option<String>,
+    /// `[profiles.<name>]` table to apply (defaults to `LEADERBOARD_PROFILE`)
+    pub profile: Option<String>,
+    /// Values given on the command line, keyed like the config file
+    pub overrides: Vec<(&'static str, String)>,
+}
+
+/// A setting's value and the layer it came from, for error messages.
+struct Setting {
+    value: String,
+    origin: String,
 }
 
 impl Config {
-    pub fn from_env() -> Result<Self> {
+    /// Builds the configuration from, lowest precedence first: built-in
+    /// defaults, the config file, its selected profile, environment variables
+    /// and command-line overrides. Every invalid setting is reported in one
+    /// error rather than stopping at the first.
+    pub fn load(sources: &ConfigSources) -> Result<Self> {
+        let path = sources
+            .path
+            .clone()
+            .or_else(|| non_empty_var("LEADERBOARD_CONFIG"))
+            .or_else(|| {
+                Path::new(DEFAULT_CONFIG_FILE)
+                    .exists()
+                    .then(|| DEFAULT_CONFIG_FILE.to_string())
+            });
+        let profile = sources
+            .profile
+            .clone()
+            .or_else(|| non_empty_var("LEADERBOARD_PROFILE"));
+
+        let file = match &path {
+            Some(path) => Some((
+                path.as_str(),
+                fs::read_to_string(path)
+                    .map_err(|e| anyhow!("Failed to read config file {}: {}", path, e))?,
+            )),
+            None => None,
+        };
+
+        Self::from_layers(
+            file.as_ref()
+                .map(|(path, contents)| (*path, contents.as_str())),
+            profile.as_deref(),
+            non_empty_var,
+            &sources.overrides,
+        )
+    }
+
+    fn from_layers(
+        file: Option<(&str, &str)>,
+        profile: Option<&str>,
+        env: impl Fn(&str) -> Option<String>,
+        overrides: &[(&'static str, String)],
+    ) -> Result<Self> {
+        let mut layers = Layers::default();
+
+        match file {
+            Some((path, contents)) => layers.read_file(path, contents, profile),
+            None => {
+                if let Some(profile) = profile {
+                    layers.errors.push(format!(
+                        "Profile '{}' was requested but no config file was found (use --config or LEADERBOARD_CONFIG)",
+                        profile
+                    ));
+                }
+            }
+        }
+        for (key, var) in SETTINGS {
+            if let Some(value) = env(var).filter(|value| !value.trim().is_empty()) {
+                layers.set(key, value, format!("environment variable {}", var));
+            }
+        }
+        for (key, value) in overrides {
+            layers.set(key, value.clone(), "command line".to_string());
+        }
+
+        let networks = match NetworkRegistry::load(layers.string("networks_path").as_deref()) {
+            Ok(networks) => Some(networks),
+            Err(e) => {
+                layers.errors.push(format!("networks: {}", e));
+                None
+            }
+        };
+
+        let default_port = layers.parse("default_port", |_| true).unwrap_or(3001);
+        let default_limit = layers
+            .parse::<usize>("default_limit", |n| *n > 0)
+            .unwrap_or(20);
+        let target_swaps = layers.parse::<usize>("target_swaps", |n| *n > 0);
+        let batch_size = layers
+            .parse::<usize>("batch_size", |n| *n > 0)
+            .unwrap_or(1000);
+        let max_retries = layers.parse("max_retries", |_| true).unwrap_or(3);
+        let retry_base_delay_ms = layers.parse("retry_base_delay_ms", |_| true).unwrap_or(500);
+        let request_timeout_secs = layers
+            .parse::<u64>("request_timeout_secs", |n| *n > 0)
+            .unwrap_or(30);
+        let cache_ttl_secs = layers.parse("cache_ttl_secs", |_| true).unwrap_or(60);
+        let refresh_interval_secs = layers
+            .parse::<u64>("refresh_interval_secs", |n| *n > 0)
+            .unwrap_or(300);
+        let swap_source = layers.parse("swap_source", |_| true).unwrap_or_default();
+        let factory_address = layers.parse("factory_address", |address: &String| {
+            is_valid_address(address)
+        });
+        let rpc_log_range = layers
+            .parse::<u64>("rpc_log_range", |n| *n > 0)
+            .unwrap_or(10_000);
+        let fixture_path = layers.string("fixture_path");
+        let labels_path = layers.string("labels_path");
+        let token_map_path = layers.string("token_map_path");
+
+        if swap_source == SourceKind::Fixture && fixture_path.is_none() {
+            layers.errors.push(
+                "swap_source is fixture but no fixture_path (SWAP_FIXTURE_PATH) is set".to_string(),
+            );
+        }
+
+        // Registries are checked here so a bad file fails at startup rather
+        // than on the first request that reads it
+        let mut watchlist = Vec::new();
+        if let Some(networks) = &networks {
+            let entries = layers.string("watchlist").unwrap_or_default();
+            for entry in entries.split(',').filter(|entry| !entry.trim().is_empty()) {
+                match WatchedToken::parse(entry, networks) {
+                    Ok(token) => watchlist.push(token),
+                    Err(e) => layers.errors.push(format!("watchlist: {}", e)),
+                }
+            }
+
+            let ids: Vec<&str> = networks.ids().collect();
+            if let Err(e) = LabelRegistry::load_networks(&ids, labels_path.as_deref()) {
+                layers.errors.push(format!("labels: {}", e));
+            }
+            if let Err(e) = TokenMap::load(token_map_path.as_deref(), networks) {
+                layers.errors.push(format!("token map: {}", e));
+            }
+        }
+
+        let Some(networks) = networks.filter(|_| layers.errors.is_empty()) else {
+            return Err(anyhow!(
+                "Invalid configuration:\n  - {}",
+                layers.errors.join("\n  - ")
+            ));
+        };
+
         Ok(Self {
-            graph_api_key: env::var("GRAPH_API_KEY")
-                .unwrap_or_else(|_| "e945e8b23d8af7b0f249e0a260e6768d".to_string()),
-            default_port: env::var("DEFAULT_PORT")
-                .unwrap_or_else(|_| "3001".to_string())
-                .parse()
-                .map_err(|_| anyhow!("Invalid DEFAULT_PORT value"))?,
-            default_limit: env::var("DEFAULT_LIMIT")
-                .unwrap_or_else(|_| "20".to_string())
-                .parse()
-                .map_err(|_| anyhow!("Invalid DEFAULT_LIMIT value"))?,
-            target_swaps: env::var("TARGET_SWAPS")
-                .unwrap_or_else(|_| "2000".to_string())
-                .parse()
-                .map_err(|_| anyhow!("Invalid TARGET_SWAPS value"))?,
-            batch_size: env::var("BATCH_SIZE")
-                .unwrap_or_else(|_| "1000".to_string())
-                .parse()
-                .map_err(|_| anyhow!("Invalid BATCH_SIZE value"))?,
+            graph_api_key: layers.string("graph_api_key"),
+            subgraph_url: layers.string("subgraph_url"),
+            default_port,
+            default_limit,
+            target_swaps,
+            batch_size,
+            max_retries,
+            retry_base_delay: Duration::from_millis(retry_base_delay_ms),
+            request_timeout: Duration::from_secs(request_timeout_secs),
+            cache_ttl: Duration::from_secs(cache_ttl_secs),
+            watchlist,
+            refresh_interval: Duration::from_secs(refresh_interval_secs),
+            admin_token: layers.string("admin_token"),
+            swap_source,
+            rpc_url: layers.string("rpc_url"),
+            factory_address,
+            rpc_log_range,
+            fixture_path,
+            labels_path,
+            store_path: layers.string("store_path"),
+            token_map_path,
+            networks,
+            config_path: file.map(|(path, _)| path.to_string()),
+            profile: profile.map(str::to_string),
         })
     }
 }
 
-#[derive(Debug)]
-pub struct NetworkConfig {
-    pub subgraph_id: &'static str,
-    pub default_start_block_offset: u64,
-    pub name: &'static str,
+#[derive(Default)]
+struct Layers {
+    values: BTreeMap<&'static str, Setting>,
+    errors: Vec<String>,
 }
 
-impl NetworkConfig {
-    pub fn get(network: &str) -> Result<Self> {
-        match network.to_lowercase().as_str() {
-            "ethereum" | "mainnet" => Ok(Self {
-                subgraph_id: "5zvR82QoaXYFyDEKLZ9t6v9adgnptxYpKpSbxtgVENFV",
-                default_start_block_offset: 216_000, // ~30 days for Ethereum
-                name: "Ethereum",
-            }),
-            "arbitrum" => Ok(Self {
-                subgraph_id: "FbCGRftH4a3yZugY7TnbYgPJVEv2LvMT6oF1fxPe9aJM",
-                default_start_block_offset: 2_160_000, // ~30 days for Arbitrum
-                name: "Arbitrum One",
-            }),
-            "polygon" => Ok(Self {
-                subgraph_id: "3hCPRGf4z88VC5rsBKU5AA9FBBq5nF3jbKJG7VZCbhjm",
-                default_start_block_offset: 1_296_000, // ~30 days for Polygon
-                name: "Polygon",
-            }),
-            "optimism" => Ok(Self {
-                subgraph_id: "Cghf4LfVqPiFw6fp6Y5X5Ubc8UpmUhSfJL82zwiBFLaj",
-                default_start_block_offset: 432_000, // ~30 days for Optimism
-                name: "Optimism",
-            }),
-            "base" => Ok(Self {
-                subgraph_id: "HMuAwufqZ1YCRmzL2SfHTVkzZovC9VL2UAKhjvRqKiR1",
-                default_start_block_offset: 432_000, // ~30 days for Base
-                name: "Base",
-            }),
-            _ => Err(anyhow!(
-                "Unsupported network: {}. Supported networks: ethereum, arbitrum, polygon, optimism, base",
-                network
+impl Layers {
+    fn set(&mut self, key: &str, value: String, origin: String) {
+        match SETTINGS.iter().find(|(name, _)| *name == key) {
+            Some((name, _)) => {
+                self.values.insert(name, Setting { value, origin });
+            }
+            None => self
+                .errors
+                .push(format!("Unknown setting '{}' in {}", key, origin)),
+        }
+    }
+
+    /// Applies the file's top-level settings, then those of `profile`.
+    fn read_file(&mut self, path: &str, contents: &str, profile: Option<&str>) {
+        let mut table: toml::Table = match toml::from_str(contents) {
+            Ok(table) => table,
+            Err(e) => {
+                self.errors
+                    .push(format!("Failed to parse config file {}: {}", path, e));
+                return;
+            }
+        };
+
+        let profiles = match table.remove("profiles") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            Some(_) => {
+                self.errors
+                    .push(format!("'profiles' in {} must be a table", path));
+                toml::Table::new()
+            }
+            None => toml::Table::new(),
+        };
+        self.set_table(table, path.to_string());
+
+        let Some(profile) = profile else {
+            return;
+        };
+        match profiles.get(profile) {
+            Some(toml::Value::Table(settings)) => {
+                self.set_table(settings.clone(), format!("{} [profiles.{}]", path, profile))
+            }
+            Some(_) => self.errors.push(format!(
+                "[profiles.{}] in {} must be a table",
+                profile, path
             )),
+            None => {
+                let available: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                self.errors.push(format!(
+                    "Profile '{}' is not defined in {}. Available profiles: {}",
+                    profile,
+                    path,
+                    if available.is_empty() {
+                        "none".to_string()
+                    } else {
+                        available.join(", ")
+                    }
+                ));
+            }
+        }
+    }
+
+    fn set_table(&mut self, table: toml::Table, origin: String) {
+        for (key, value) in table {
+            match toml_string(&value) {
+                Some(value) => self.set(&key, value, origin.clone()),
+                None => self.errors.push(format!(
+                    "Setting '{}' in {} must be a string, number, boolean or list",
+                    key, origin
+                )),
+            }
+        }
+    }
+
+    fn string(&self, key: &str) -> Option<String> {
+        self.values
+            .get(key)
+            .map(|setting| setting.value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Parses a setting, recording an error naming where a bad value came
+    /// from. Unset and invalid settings both return `None`.
+    fn parse<T>(&mut self, key: &str, valid: impl Fn(&T) -> bool) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let setting = self.values.get(key)?;
+        let value = setting.value.trim();
+        if value.is_empty() {
+            return None;
         }
+        let error = match value.parse::<T>() {
+            Ok(parsed) if valid(&parsed) => return Some(parsed),
+            Ok(_) => "value is out of range".to_string(),
+            Err(e) => e.to_string(),
+        };
+        self.errors.push(format!(
+            "Invalid {} '{}' from {}: {}",
+            key, value, setting.origin, error
+        ));
+        None
+    }
+}
+
+/// Config file values as the strings the environment would hold; lists are
+/// joined with commas, as in `WATCHLIST`.
+fn toml_string(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Integer(n) => Some(n.to_string()),
+        toml::Value::Float(n) => Some(n.to_string()),
+        toml::Value::Boolean(b) => Some(b.to_string()),
+        toml::Value::Array(items) => items
+            .iter()
+            .map(toml_string)
+            .collect::<Option<Vec<_>>>()
+            .map(|items| items.join(",")),
+        _ => None,
+    }
+}
+
+fn non_empty_var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|value| !value.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"
+        default_limit = 50
+        batch_size = 500
+        watchlist = ["0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"]
+
+        [profiles.prod]
+        default_limit = 100
+        swap_source = "rpc"
+        rpc_url = "http://prod-node:8545"
+    "#;
+
+    fn env(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        move |key| {
+            vars.iter()
+                .find(|(var, _)| *var == key)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_config_load() {
+        // No file, no environment and no flags: every setting falls back to its default
+        let config = Config::from_layers(None, None, env(&[]), &[]).unwrap();
+        assert_eq!(config.default_port, 3001);
+        assert_eq!(config.default_limit, 20);
+        assert_eq!(config.swap_source, SourceKind::Subgraph);
+        assert!(config.graph_api_key.is_none());
+        assert!(config.store_path.is_none());
+        assert!(config.networks.get("ethereum").is_ok());
+    }
+
+    #[test]
+    fn test_layers_apply_in_order_and_errors_are_collected() {
+        let config = Config::from_layers(
+            Some(("leaderboard.toml", FILE)),
+            Some("prod"),
+            env(&[("BATCH_SIZE", "250"), ("RPC_URL", "http://env-node:8545")]),
+            &[("rpc_url", "http://cli-node:8545".to_string())],
+        )
+        .unwrap();
+        assert_eq!(config.default_limit, 100);
+        assert_eq!(config.batch_size, 250);
+        assert_eq!(config.swap_source, SourceKind::Rpc);
+        assert_eq!(config.rpc_url.as_deref(), Some("http://cli-node:8545"));
+        assert_eq!(config.watchlist.len(), 1);
+        assert_eq!(config.default_port, 3001);
+        assert!(config.graph_api_key.is_none());
+
+        let error = Config::from_layers(
+            Some(("leaderboard.toml", "batch_sise = 10\n[profiles.dev]\n")),
+            Some("prod"),
+            env(&[("DEFAULT_PORT", "http"), ("REQUEST_TIMEOUT_SECS", "0")]),
+            &[],
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("Unknown setting 'batch_sise' in leaderboard.toml"));
+        assert!(error.contains("Profile 'prod' is not defined"));
+        assert!(
+            error.contains("Invalid default_port 'http' from environment variable DEFAULT_PORT")
+        );
+        assert!(error.contains("Invalid request_timeout_secs '0'"));
     }
 }
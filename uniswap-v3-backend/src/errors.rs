@@ -0,0 +1,199 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+
+use crate::client::ClientError;
+
+/// Machine-readable error kinds returned by the HTTP API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidRequest,
+    InvalidAddress,
+    Unauthorized,
+    UnsupportedNetwork,
+    NotFound,
+    UpstreamAuth,
+    RateLimited,
+    UpstreamUnavailable,
+    UpstreamTimeout,
+    UpstreamParseError,
+    UpstreamError,
+    /// The server has no usable swap source for the network, e.g. a missing
+    /// API key or RPC endpoint
+    SourceUnavailable,
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn status(self) -> StatusCode {
+        match self {
+            Self::InvalidRequest | Self::InvalidAddress => StatusCode::BAD_REQUEST,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::UnsupportedNetwork | Self::NotFound => StatusCode::NOT_FOUND,
+            Self::UpstreamAuth | Self::UpstreamParseError | Self::UpstreamError => {
+                StatusCode::BAD_GATEWAY
+            }
+            Self::RateLimited | Self::UpstreamUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::UpstreamTimeout => StatusCode::GATEWAY_TIMEOUT,
+            Self::SourceUnavailable | Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Whether repeating the same request later may succeed
+    pub fn retryable(self) -> bool {
+        matches!(
+            self,
+            Self::RateLimited | Self::UpstreamUnavailable | Self::UpstreamTimeout
+        )
+    }
+}
+
+/// JSON error body: `{ "code", "message", "details", "retryable" }`.
+#[derive(Debug, Serialize)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub details: Option<String>,
+    pub retryable: bool,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+            retryable: code.retryable(),
+        }
+    }
+
+    pub fn with_details(mut self, details: impl ToString) -> Self {
+        self.details = Some(details.to_string());
+        self
+    }
+
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidRequest, message)
+    }
+
+    pub fn internal(message: impl Into<String>, error: impl ToString) -> Self {
+        Self::new(ErrorCode::Internal, message).with_details(error)
+    }
+
+    /// A swap source that could not be built. That is server misconfiguration,
+    /// so the cause is logged here instead of being sent to the client.
+    pub fn source_unavailable(network: &str, error: &anyhow::Error) -> Self {
+        eprintln!(
+            "Failed to build the swap source for network {}: {}",
+            network, error
+        );
+        Self::new(
+            ErrorCode::SourceUnavailable,
+            format!("No swap source is available for network {}", network),
+        )
+    }
+
+    /// Classifies a failure from a swap source into the matching API error.
+    pub fn upstream(error: &anyhow::Error) -> Self {
+        let code = classify_upstream(error);
+        let message = match code {
+            ErrorCode::InvalidRequest => "The upstream data source rejected the request",
+            ErrorCode::UpstreamAuth => "The upstream data source rejected our credentials",
+            ErrorCode::RateLimited => "The upstream data source is rate limiting requests",
+            ErrorCode::UpstreamUnavailable => "The upstream data source is unavailable",
+            ErrorCode::UpstreamTimeout => "The upstream data source timed out",
+            ErrorCode::UpstreamParseError => {
+                "The upstream data source returned an unreadable response"
+            }
+            _ => "The upstream data source returned an error",
+        };
+        Self::new(code, message).with_details(error)
+    }
+}
+
+fn classify_upstream(error: &anyhow::Error) -> ErrorCode {
+    let Some(error) = error.downcast_ref::<ClientError>() else {
+        return ErrorCode::UpstreamError;
+    };
+
+    match error {
+        ClientError::InvalidInput(_) => ErrorCode::InvalidRequest,
+        ClientError::Transport(e) if e.is_timeout() => ErrorCode::UpstreamTimeout,
+        ClientError::Transport(e) if e.is_connect() => ErrorCode::UpstreamUnavailable,
+        ClientError::Transport(_) => ErrorCode::UpstreamError,
+        ClientError::Http { status, .. } => match status {
+            429 => ErrorCode::RateLimited,
+            504 => ErrorCode::UpstreamTimeout,
+            500..=599 => ErrorCode::UpstreamUnavailable,
+            _ => ErrorCode::UpstreamError,
+        },
+        ClientError::Auth(_) => ErrorCode::UpstreamAuth,
+        ClientError::IndexerUnavailable(_) => ErrorCode::UpstreamUnavailable,
+        ClientError::SubgraphNotFound(_) | ClientError::GraphQl(_) => ErrorCode::UpstreamError,
+        ClientError::Decode(_) => ErrorCode::UpstreamParseError,
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match &self.details {
+            Some(details) => eprintln!(
+                "Request failed ({:?}): {}: {}",
+                self.code, self.message, details
+            ),
+            None => eprintln!("Request failed ({:?}): {}", self.code, self.message),
+        }
+        (self.code.status(), Json(self)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upstream_errors_map_to_status_and_retryability() {
+        let cases = [
+            (
+                ClientError::Http {
+                    status: 429,
+                    body: "slow down".to_string(),
+                    retry_after: None,
+                },
+                503,
+                true,
+            ),
+            (
+                ClientError::IndexerUnavailable("bad indexers".to_string()),
+                503,
+                true,
+            ),
+            (ClientError::Auth("invalid API key".to_string()), 502, false),
+            (ClientError::Decode("EOF".to_string()), 502, false),
+            (
+                ClientError::InvalidInput("bad address".to_string()),
+                400,
+                false,
+            ),
+        ];
+
+        for (error, status, retryable) in cases {
+            let api_error = ApiError::upstream(&error.into());
+            assert_eq!(api_error.code.status().as_u16(), status);
+            assert_eq!(api_error.retryable, retryable);
+        }
+
+        let other = ApiError::upstream(&anyhow::anyhow!("rpc node went away"));
+        assert_eq!(other.code, ErrorCode::UpstreamError);
+
+        let misconfigured = ApiError::source_unavailable(
+            "base",
+            &anyhow::anyhow!("The rpc swap source needs an endpoint"),
+        );
+        assert_eq!(misconfigured.code.status().as_u16(), 500);
+        assert!(misconfigured.details.is_none());
+    }
+}
@@ -1,163 +1,1095 @@
-use axum::{http::StatusCode, response::Json};
-use rust_decimal::Decimal;
+use axum::{
+    extract::{Path, Query, State, rejection::QueryRejection},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{
+        IntoResponse, Json, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use ethers::utils::hex;
+use futures::stream::{self, Stream, StreamExt};
 use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
 
-use crate::client::UniswapClient;
+use crate::cache::etag;
+use crate::config::Config;
+use crate::errors::{ApiError, ErrorCode};
+use crate::labels::{AddressCategory, LabelRegistry};
+use crate::live;
+use crate::multichain::{merge_network_stats, network_list};
+use crate::networks::{NetworkConfig, NetworkRegistry};
+use crate::pnl::CostBasisMode;
+use crate::pools::{aggregate_pool_stats, rank_pools};
+use crate::refresher::{WarmSwaps, WatchStatus, WatchedToken};
+use crate::server::AppState;
+use crate::sources::build_swap_source;
+use crate::tokens::{ChainToken, TokenMap};
+use crate::trader::trader_history;
 use crate::types::{
-    LeaderboardRequest, LeaderboardResponse, SummaryStats, TraderStatsAPI,
+    AggregationOptions, AttributionMode, LeaderboardQuery, LeaderboardRequest, LeaderboardResponse,
+    PageInfo, PoolFilter, PoolLeaderboardResponse, PoolsQuery, SortKey, SummaryStats, Swap,
+    SwapWindow, TraderDetailResponse, TraderQuery, TraderStatsAPI,
+};
+use crate::utils::{
+    TimeBound, aggregate_trader_stats, generate_demo_data, is_valid_address, parse_time_bound,
+    rank_traders,
 };
-use crate::utils::{aggregate_trader_stats, generate_demo_data, get_default_start_block};
 
 pub async fn health_check() -> &'static str {
     "Uniswap V3 Leaderboard API is running!"
 }
 
+pub async fn list_watchlist(State(state): State<AppState>) -> Json<Vec<WatchStatus>> {
+    Json(state.watchlist.status())
+}
+
+pub async fn add_to_watchlist(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<WatchedToken>,
+) -> Result<StatusCode, ApiError> {
+    authorize_admin(&state, &headers)?;
+    let token = watched_token(&state, &request)?;
+    if state.watchlist.add(token) {
+        Ok(StatusCode::CREATED)
+    } else {
+        Ok(StatusCode::OK)
+    }
+}
+
+pub async fn remove_from_watchlist(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<WatchedToken>,
+) -> Result<StatusCode, ApiError> {
+    authorize_admin(&state, &headers)?;
+    let token = watched_token(&state, &request)?;
+    if state.watchlist.remove(&token) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::new(
+            ErrorCode::NotFound,
+            "Token is not on the watchlist",
+        ))
+    }
+}
+
+/// Streams live updates for a watched token as Server-Sent Events: a
+/// `snapshot` of the current top traders once the token is warm, then an
+/// `update` event after every refresh that brought new swaps or rank changes.
+pub async fn leaderboard_stream(
+    State(state): State<AppState>,
+    Query(request): Query<WatchedToken>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let token = watched_token(&state, &request)?;
+    let Some(receiver) = state.watchlist.subscribe(&token) else {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            "Only watched tokens can be streamed",
+        ));
+    };
+
+    let snapshot = state
+        .watchlist
+        .warm(&token)
+        .map(|warm| live::snapshot(&token, &warm))
+        .and_then(|snapshot| json_event("snapshot", &snapshot));
+
+    let updates = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(update) => return Some((json_event("update", &*update), receiver)),
+                // A slow client skips the updates it missed
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    let events = stream::iter(snapshot)
+        .chain(updates.filter_map(|event| async move { event }))
+        .map(Ok);
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+fn json_event(name: &str, data: &impl serde::Serialize) -> Option<Event> {
+    Event::default()
+        .event(name)
+        .json_data(data)
+        .map_err(|e| eprintln!("Failed to encode {} event: {}", name, e))
+        .ok()
+}
+
+fn watched_token(state: &AppState, request: &WatchedToken) -> Result<WatchedToken, ApiError> {
+    WatchedToken::new(
+        &state.config.networks,
+        &request.network,
+        &request.token_address,
+    )
+    .map_err(|e| ApiError::invalid_request("Invalid watchlist entry").with_details(e))
+}
+
+/// Watchlist changes need `Authorization: Bearer <ADMIN_TOKEN>`; without an
+/// `ADMIN_TOKEN` configured they are disabled entirely.
+fn authorize_admin(state: &AppState, headers: &HeaderMap) -> Result<(), ApiError> {
+    let Some(expected) = state.config.admin_token.as_deref() else {
+        return Err(ApiError::new(
+            ErrorCode::Unauthorized,
+            "Admin endpoints are disabled; set ADMIN_TOKEN to enable them",
+        ));
+    };
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if provided != Some(expected) {
+        return Err(ApiError::new(
+            ErrorCode::Unauthorized,
+            "Missing or invalid admin token",
+        ));
+    }
+    Ok(())
+}
+
+/// Serves leaderboards from the response cache, computing each distinct
+/// request at most once per TTL. Responses carry an `ETag`, and a matching
+/// `If-None-Match` gets an empty 304.
 pub async fn leaderboard_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<LeaderboardRequest>,
-) -> Result<Json<LeaderboardResponse>, StatusCode> {
+) -> Result<Response, ApiError> {
     println!("Received leaderboard request: {:?}", payload);
+    cached_leaderboard(&state, &headers, payload).await
+}
+
+/// `GET /api/v1/{network}/tokens/{address}/leaderboard`: the same leaderboard
+/// as `POST /api/leaderboard`, addressed by URL so it can be bookmarked and
+/// cached by intermediaries.
+pub async fn token_leaderboard_handler(
+    State(state): State<AppState>,
+    Path((network, token_address)): Path<(String, String)>,
+    headers: HeaderMap,
+    query: Result<Query<LeaderboardQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let query = parse_query(query, "leaderboard")?;
+    println!(
+        "Received leaderboard request for {} on {}: {:?}",
+        token_address, network, query
+    );
+
+    let now = cache_epoch(chrono::Utc::now(), state.config.cache_ttl);
+    let payload = query_to_request(network, token_address, query, now)?;
+    cached_leaderboard(&state, &headers, payload).await
+}
+
+/// `now` rounded down to a multiple of the cache TTL. Relative bounds such as
+/// `from=7d` then resolve to the same timestamps, and so share a cache entry
+/// and ETag, for a whole TTL instead of changing every second.
+fn cache_epoch(
+    now: chrono::DateTime<chrono::Utc>,
+    ttl: std::time::Duration,
+) -> chrono::DateTime<chrono::Utc> {
+    let step = ttl.as_secs().max(1) as i64;
+    let seconds = now.timestamp() - now.timestamp().rem_euclid(step);
+    chrono::DateTime::from_timestamp(seconds, 0).unwrap_or(now)
+}
+
+fn query_to_request(
+    network: String,
+    token_address: String,
+    query: LeaderboardQuery,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<LeaderboardRequest, ApiError> {
+    let exclude_categories = query
+        .exclude
+        .map(|exclude| {
+            exclude
+                .split(',')
+                .map(str::trim)
+                .filter(|category| !category.is_empty())
+                .map(|category| {
+                    <AddressCategory as clap::ValueEnum>::from_str(category, true).map_err(|_| {
+                        ApiError::invalid_request(format!("Unknown address category: {}", category))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?;
+    let pools = query.pools.map(|pools| comma_list(&pools));
+    let fee_tiers = query
+        .fee_tiers
+        .map(|fee_tiers| parse_fee_tiers(&fee_tiers))
+        .transpose()?;
+
+    let networks = network_list(&network);
+    Ok(LeaderboardRequest {
+        token_address: Some(token_address),
+        start_block: query.start_block,
+        end_block: query.end_block,
+        from_timestamp: query_time_bound(query.from, TimeBound::Start, "from", now)?,
+        to_timestamp: query_time_bound(query.to, TimeBound::End, "to", now)?,
+        limit: query.limit,
+        offset: query.offset,
+        cursor: query.cursor,
+        attribution: query.attribution,
+        attribution_fallback: query.attribution_fallback,
+        pnl_mode: query.pnl_mode,
+        sort_by: query.sort_by,
+        order: query.order,
+        exclude_categories,
+        demo: query.demo,
+        network: networks.is_none().then_some(network),
+        networks,
+        pools,
+        fee_tiers,
+        pool_breakdown: query.pool_breakdown,
+    })
+}
+
+fn comma_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_fee_tiers(value: &str) -> Result<Vec<u32>, ApiError> {
+    comma_list(value)
+        .iter()
+        .map(|fee| {
+            fee.parse().map_err(|_| {
+                ApiError::invalid_request(format!(
+                    "Invalid fee tier: {}. Use hundredths of a basis point, e.g. 500 for 0.05%",
+                    fee
+                ))
+            })
+        })
+        .collect()
+}
+
+fn query_time_bound(
+    value: Option<String>,
+    bound: TimeBound,
+    name: &str,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<Option<u64>, ApiError> {
+    value
+        .map(|value| parse_time_bound(&value, bound, now))
+        .transpose()
+        .map_err(|e| ApiError::invalid_request(format!("Invalid `{}`", name)).with_details(e))
+}
+
+fn parse_query<T>(query: Result<Query<T>, QueryRejection>, endpoint: &str) -> Result<T, ApiError> {
+    query.map(|Query(query)| query).map_err(|e| {
+        ApiError::invalid_request(format!("Invalid {} query parameters", endpoint))
+            .with_details(e.body_text())
+    })
+}
+
+/// The block and `from`/`to` bounds of a trader or pools query as a window,
+/// rejecting bounds that leave it empty.
+fn parse_window(
+    start_block: Option<u64>,
+    end_block: Option<u64>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<SwapWindow, ApiError> {
+    let now = chrono::Utc::now();
+    let window = SwapWindow {
+        start_block,
+        end_block,
+        from_timestamp: query_time_bound(from, TimeBound::Start, "from", now)?,
+        to_timestamp: query_time_bound(to, TimeBound::End, "to", now)?,
+    };
+    if let (Some(start), Some(end)) = (window.start_block, window.end_block)
+        && start > end
+    {
+        return Err(ApiError::invalid_request(format!(
+            "Invalid block range: start_block {} > end_block {}",
+            start, end
+        )));
+    }
+    if let (Some(from), Some(to)) = (window.from_timestamp, window.to_timestamp)
+        && from > to
+    {
+        return Err(ApiError::invalid_request(format!(
+            "Invalid time window: from {} > to {}",
+            from, to
+        )));
+    }
+    Ok(window)
+}
+
+/// `GET /api/v1/{network}/tokens/{token}/traders/{address}`: one trader's
+/// stats, per-pool breakdown and a page of their swaps, newest first.
+pub async fn trader_handler(
+    State(state): State<AppState>,
+    Path((network, token, address)): Path<(String, String, String)>,
+    query: Result<Query<TraderQuery>, QueryRejection>,
+) -> Result<Json<TraderDetailResponse>, ApiError> {
+    let query = parse_query(query, "trader")?;
+    println!(
+        "Received trader request for {} on {} {}: {:?}",
+        address, network, token, query
+    );
+
+    validate_address(&token, "token")?;
+    validate_address(&address, "trader")?;
+    let config = &state.config;
+    let network_config = network_config(&config.networks, &network)?;
+    let labels = LabelRegistry::load(&network_config.id, config.labels_path.as_deref())
+        .map_err(|e| ApiError::internal("Failed to load address labels", e))?;
+
+    let mut window = parse_window(query.start_block, query.end_block, query.from, query.to)?;
+
+    let options = AggregationOptions {
+        attribution: query.attribution.unwrap_or_default(),
+        attribution_fallback: query
+            .attribution_fallback
+            .unwrap_or(AttributionMode::Recipient),
+        cost_basis: query.pnl_mode,
+        pool_breakdown: false,
+    };
+    let token = token.to_lowercase();
+    let swaps = load_swaps(
+        &state,
+        config,
+        network_config,
+        &token,
+        &mut window,
+        &PoolFilter::default(),
+    )
+    .await?;
+    let history = trader_history(swaps.as_slice(), &token, &address, &options)
+        .map_err(|e| ApiError::internal("Failed to build trader history", e))?
+        .ok_or_else(|| {
+            ApiError::new(
+                ErrorCode::NotFound,
+                "No swaps are credited to this trader in the requested window",
+            )
+            .with_details(&address)
+        })?;
+
+    let offset = query.offset.unwrap_or(0);
+    let limit = query.limit.unwrap_or(50).min(MAX_TRADER_SWAPS_PAGE);
+    Ok(Json(TraderDetailResponse {
+        trader: TraderStatsAPI::from_stats(history.stats, &labels),
+        pools: history.pools,
+        total_swaps: history.swaps.len(),
+        swaps: history.swaps.into_iter().skip(offset).take(limit).collect(),
+        offset,
+        limit,
+        window,
+    }))
+}
+
+/// `GET /api/v1/{network}/tokens/{token}/pools`: the pools trading a token,
+/// ranked by volume, swap count, unique traders or TVL.
+pub async fn pools_handler(
+    State(state): State<AppState>,
+    Path((network, token)): Path<(String, String)>,
+    query: Result<Query<PoolsQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let query = parse_query(query, "pools")?;
+    println!(
+        "Received pools request for {} on {}: {:?}",
+        token, network, query
+    );
+
+    validate_address(&token, "token")?;
+    let pools = query.pools.as_deref().map(comma_list).unwrap_or_default();
+    for pool in &pools {
+        validate_address(pool, "pool")?;
+    }
+    let fee_tiers = query
+        .fee_tiers
+        .as_deref()
+        .map(parse_fee_tiers)
+        .transpose()?
+        .unwrap_or_default();
+    let config = &state.config;
+    let network_config = network_config(&config.networks, &network)?;
+
+    let mut window = parse_window(query.start_block, query.end_block, query.from, query.to)?;
+
+    let options = AggregationOptions {
+        attribution: query.attribution.unwrap_or_default(),
+        attribution_fallback: query
+            .attribution_fallback
+            .unwrap_or(AttributionMode::Recipient),
+        ..AggregationOptions::default()
+    };
+    let token = token.to_lowercase();
+    let selected = PoolFilter::new(&pools, &fee_tiers);
+    let swaps = load_swaps(
+        &state,
+        config,
+        network_config,
+        &token,
+        &mut window,
+        &selected,
+    )
+    .await?;
+    let pools = aggregate_pool_stats(swaps.as_slice(), &token, &options)
+        .map_err(|e| ApiError::internal("Failed to aggregate pool stats", e))?;
+    let pools = rank_pools(
+        pools,
+        query.sort_by.unwrap_or_default(),
+        query.order.unwrap_or_default(),
+    );
+
+    let limit = query.limit.unwrap_or(20).min(pools.len());
+    Ok(Json(PoolLeaderboardResponse {
+        pools: &pools[..limit],
+        total_pools: pools.len(),
+        window: &window,
+    })
+    .into_response())
+}
+
+/// Largest page of swaps the trader endpoint returns
+const MAX_TRADER_SWAPS_PAGE: usize = 500;
+
+/// Serves a leaderboard from the response cache, computing it on a miss, with
+/// `Cache-Control`/`ETag` headers and `If-None-Match` support.
+async fn cached_leaderboard(
+    state: &AppState,
+    headers: &HeaderMap,
+    payload: LeaderboardRequest,
+) -> Result<Response, ApiError> {
+    let payload = normalize_request(payload, &state.config.networks);
+    if payload.offset.is_some() && payload.cursor.is_some() {
+        return Err(ApiError::invalid_request(
+            "Pass either offset or cursor, not both",
+        ));
+    }
+
+    let (ranking, key) = ranking_request(&payload)?;
+    let cache = &state.leaderboard_cache;
+    let cached = cache
+        .get_or_try_insert(key, || build_leaderboard(state, ranking))
+        .await?;
+
+    let leaderboard = &cached.value;
+    let offset = match &payload.cursor {
+        Some(cursor) => leaderboard.resume_offset(cursor)?,
+        None => payload.offset.unwrap_or(0),
+    };
+    let body = serde_json::to_vec(&leaderboard.page(offset, payload.limit.unwrap_or(20)))
+        .map_err(|e| ApiError::internal("Failed to serialize leaderboard", e))?;
+    let etag = etag(&body);
+
+    let max_age = cache.ttl_remaining(&cached).as_secs();
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_str(&format!("public, max-age={}", max_age))
+            .expect("cache-control header is ASCII"),
+    );
+    response_headers.insert(
+        header::ETAG,
+        HeaderValue::from_str(&etag).expect("ETag is hex"),
+    );
+
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .any(|tag| tag.trim() == etag || tag.trim() == "*")
+        });
+    if not_modified {
+        return Ok((StatusCode::NOT_MODIFIED, response_headers).into_response());
+    }
+
+    response_headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    Ok((StatusCode::OK, response_headers, body).into_response())
+}
+
+/// The ranking every page of `payload` shares, and its cache key.
+fn ranking_request(payload: &LeaderboardRequest) -> Result<(LeaderboardRequest, String), ApiError> {
+    let ranking = LeaderboardRequest {
+        limit: None,
+        offset: None,
+        cursor: None,
+        ..payload.clone()
+    };
+    let key = serde_json::to_string(&ranking)
+        .map_err(|e| ApiError::internal("Failed to build cache key", e))?;
+    Ok((ranking, key))
+}
+
+/// Fills in defaults and canonicalizes values so that requests meaning the
+/// same thing share a cache key.
+fn normalize_request(
+    mut payload: LeaderboardRequest,
+    networks: &NetworkRegistry,
+) -> LeaderboardRequest {
+    payload.token_address = payload.token_address.map(|token| token.to_lowercase());
+    let canonical = |network: &str| {
+        networks
+            .get(network)
+            .map(|config| config.id.clone())
+            .unwrap_or_else(|_| network.to_lowercase())
+    };
+    if let Some(networks) = &mut payload.networks {
+        if networks
+            .iter()
+            .any(|network| network.eq_ignore_ascii_case("all"))
+        {
+            *networks = vec!["all".to_string()];
+        }
+        for network in networks.iter_mut() {
+            *network = canonical(network);
+        }
+        networks.sort();
+        networks.dedup();
+        payload.network = None;
+    } else {
+        payload.network = Some(canonical(payload.network.as_deref().unwrap_or("ethereum")));
+    }
+    payload.limit = Some(payload.limit.unwrap_or(20));
+    payload.attribution = Some(payload.attribution.unwrap_or_default());
+    payload.attribution_fallback = Some(
+        payload
+            .attribution_fallback
+            .unwrap_or(AttributionMode::Recipient),
+    );
+    payload.sort_by = Some(payload.sort_by.unwrap_or_default());
+    payload.order = Some(payload.order.unwrap_or_default());
+    if payload.pnl_mode.is_none() && payload.sort_by == Some(SortKey::Pnl) {
+        payload.pnl_mode = Some(CostBasisMode::Fifo);
+    }
+    if let Some(categories) = &mut payload.exclude_categories {
+        categories.sort();
+        categories.dedup();
+    }
+    if let Some(pools) = &mut payload.pools {
+        for pool in pools.iter_mut() {
+            *pool = pool.trim().to_lowercase();
+        }
+        pools.sort();
+        pools.dedup();
+    }
+    if let Some(fee_tiers) = &mut payload.fee_tiers {
+        fee_tiers.sort();
+        fee_tiers.dedup();
+    }
+    payload.pool_breakdown = Some(payload.pool_breakdown.unwrap_or(false));
+    payload.demo = Some(payload.demo.unwrap_or(false));
+    payload
+}
 
-    let trader_stats = if payload.demo.unwrap_or(false) {
+/// Every ranked trader for one request, cached once and sliced into pages.
+pub struct RankedLeaderboard {
+    traders: Vec<TraderStatsAPI>,
+    summary: SummaryStats,
+    window: SwapWindow,
+}
+
+impl RankedLeaderboard {
+    fn page(&self, offset: usize, limit: usize) -> LeaderboardResponse<'_> {
+        let start = offset.min(self.traders.len());
+        let end = start.saturating_add(limit).min(self.traders.len());
+        let traders = &self.traders[start..end];
+        let has_more = end < self.traders.len();
+
+        LeaderboardResponse {
+            traders,
+            summary: &self.summary,
+            page: PageInfo {
+                offset,
+                limit,
+                next_offset: has_more.then_some(end),
+                next_cursor: traders
+                    .last()
+                    .filter(|_| has_more)
+                    .map(|last| encode_cursor(end, &last.address)),
+            },
+            window: &self.window,
+        }
+    }
+
+    /// Offset just after the trader a cursor points at, or the offset recorded
+    /// in the cursor if that trader is no longer ranked.
+    fn resume_offset(&self, cursor: &str) -> Result<usize, ApiError> {
+        let (offset, address) = decode_cursor(cursor).ok_or_else(|| {
+            ApiError::invalid_request("Invalid pagination cursor").with_details(cursor)
+        })?;
+        Ok(self
+            .traders
+            .iter()
+            .position(|trader| trader.address == address)
+            .map_or(offset, |i| i + 1))
+    }
+}
+
+fn encode_cursor(offset: usize, address: &str) -> String {
+    hex::encode(format!("{}:{}", offset, address))
+}
+
+fn decode_cursor(cursor: &str) -> Option<(usize, String)> {
+    let decoded = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+    let (offset, address) = decoded.split_once(':')?;
+    Some((offset.parse().ok()?, address.to_string()))
+}
+
+async fn build_leaderboard(
+    state: &AppState,
+    payload: LeaderboardRequest,
+) -> Result<RankedLeaderboard, ApiError> {
+    if let (Some(from), Some(to)) = (payload.from_timestamp, payload.to_timestamp)
+        && from > to
+    {
+        return Err(ApiError::invalid_request(format!(
+            "Invalid time window: from_timestamp {} > to_timestamp {}",
+            from, to
+        )));
+    }
+
+    let mut window = SwapWindow {
+        start_block: payload.start_block,
+        end_block: payload.end_block,
+        from_timestamp: payload.from_timestamp,
+        to_timestamp: payload.to_timestamp,
+    };
+
+    for pool in payload.pools.iter().flatten() {
+        validate_address(pool, "pool")?;
+    }
+
+    let sort_by = payload.sort_by.unwrap_or_default();
+    let demo = payload.demo.unwrap_or(false);
+    let config = &state.config;
+    let chains = if demo {
+        Vec::new()
+    } else {
+        request_chains(config, &payload, &window)?
+    };
+    let label_networks: Vec<&str> = match (&payload.networks, chains.is_empty()) {
+        (_, false) => chains
+            .iter()
+            .map(|chain| chain.network.id.as_str())
+            .collect(),
+        (None, true) => vec![
+            network_config(
+                &config.networks,
+                payload.network.as_deref().unwrap_or("ethereum"),
+            )?
+            .id
+            .as_str(),
+        ],
+        (Some(_), true) => Vec::new(),
+    };
+    let labels = match LabelRegistry::load_networks(&label_networks, config.labels_path.as_deref())
+    {
+        Ok(labels) => labels,
+        Err(e) => return Err(ApiError::internal("Failed to load address labels", e)),
+    };
+
+    let mut trader_stats = if demo {
         println!("Running in demo mode");
         generate_demo_data()
     } else {
-        match &payload.token_address {
-            Some(token) => {
-                if !token.starts_with("0x") || token.len() != 42 {
-                    eprintln!("Invalid token address format: {}", token);
-                    return Err(StatusCode::BAD_REQUEST);
-                }
+        let options = AggregationOptions {
+            attribution: payload.attribution.unwrap_or_default(),
+            attribution_fallback: payload
+                .attribution_fallback
+                .unwrap_or(AttributionMode::Recipient),
+            cost_basis: payload
+                .pnl_mode
+                .or((sort_by == SortKey::Pnl).then_some(CostBasisMode::Fifo)),
+            pool_breakdown: payload.pool_breakdown.unwrap_or(false),
+        };
+        let pool_filter = PoolFilter::new(
+            payload.pools.as_deref().unwrap_or_default(),
+            payload.fee_tiers.as_deref().unwrap_or_default(),
+        );
 
-                // Additional validation for hex characters
-                if !token[2..].chars().all(|c| c.is_ascii_hexdigit()) {
-                    eprintln!("Invalid token address (non-hex characters): {}", token);
-                    return Err(StatusCode::BAD_REQUEST);
-                }
+        // Networks are fetched concurrently, each over its own copy of the window
+        let pool_filter = &pool_filter;
+        let loaded = futures::future::try_join_all(chains.iter().map(|chain| {
+            let mut window = window.clone();
+            async move {
+                let swaps = load_swaps(
+                    state,
+                    config,
+                    &chain.network,
+                    &chain.address,
+                    &mut window,
+                    pool_filter,
+                )
+                .await?;
+                Ok::<_, ApiError>((swaps, window))
+            }
+        }))
+        .await?;
 
-                let network = payload.network.as_deref().unwrap_or("ethereum");
-                let client = match UniswapClient::new(network) {
-                    Ok(client) => client,
-                    Err(e) => {
-                        eprintln!("Failed to create client for network {}: {}", network, e);
-                        return Err(StatusCode::BAD_REQUEST);
-                    }
-                };
-                let start_block = payload.start_block.unwrap_or_else(get_default_start_block);
-
-                println!("Fetching swaps for token: {}", token);
-                match client
-                    .fetch_all_swaps(token, Some(start_block), payload.end_block)
-                    .await
-                {
-                    Ok(swaps) => {
-                        if swaps.is_empty() {
-                            println!("No swaps found for token");
-                            HashMap::new()
-                        } else {
-                            match aggregate_trader_stats(&swaps, token) {
-                                Ok(stats) => stats,
-                                Err(e) => {
-                                    eprintln!("Error aggregating stats: {}", e);
-                                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
-                                }
-                            }
-                        }
-                    }
+        let mut per_network = Vec::new();
+        for (chain, (swaps, _)) in chains.iter().zip(&loaded) {
+            // Default leaderboards of watched tokens were aggregated at refresh
+            if let LoadedSwaps::Warm(warm) = swaps
+                && options == AggregationOptions::default()
+            {
+                per_network.push((chain.network.id.as_str(), warm.trader_stats.clone()));
+                continue;
+            }
+            let swaps = swaps.as_slice();
+            let stats = if swaps.is_empty() {
+                println!("No swaps found for token on {}", chain.network.name);
+                HashMap::new()
+            } else {
+                match aggregate_trader_stats(swaps, &chain.address, &options) {
+                    Ok(stats) => stats,
                     Err(e) => {
-                        eprintln!("Error fetching swaps for token {}: {}", token, e);
-
-                        // Check for specific error types to provide better responses
-                        let error_msg = e.to_string();
-                        if error_msg.contains("HTML error page")
-                            || error_msg.contains("Failed to parse API response")
-                        {
-                            eprintln!(
-                                "Token {} appears to not exist or have no Uniswap V3 pools",
-                                token
-                            );
-                            // Return empty data instead of error for better UX
-                            HashMap::new()
-                        } else {
-                            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-                        }
+                        return Err(ApiError::internal(
+                            "Failed to aggregate trader statistics",
+                            e,
+                        ));
                     }
                 }
+            };
+            per_network.push((chain.network.id.as_str(), stats));
+        }
+
+        if payload.networks.is_some() {
+            // Block numbers differ per network, so only the time bounds are shared
+            merge_network_stats(per_network)
+        } else {
+            if let Some((_, chain_window)) = loaded.into_iter().next() {
+                window = chain_window;
             }
-            None => {
-                return Err(StatusCode::BAD_REQUEST);
-            }
+            per_network
+                .pop()
+                .map(|(_, stats)| stats)
+                .unwrap_or_default()
         }
     };
 
-    // Convert to API format
-    let mut traders: Vec<TraderStatsAPI> = trader_stats
-        .into_values()
-        .map(|stats| {
-            let total_volume = stats.total_buy_volume_usd + stats.total_sell_volume_usd;
-            let net_volume = stats.total_buy_volume_token - stats.total_sell_volume_token;
-            let buy_sell_ratio = if stats.total_sells > 0 {
-                stats.total_buys as f64 / stats.total_sells as f64
-            } else if stats.total_buys > 0 {
-                stats.total_buys as f64
-            } else {
-                0.0
-            };
+    if let Some(excluded) = &payload.exclude_categories {
+        labels.exclude_categories(&mut trader_stats, excluded);
+    }
 
-            TraderStatsAPI {
-                address: stats.address,
-                total_buys: stats.total_buys,
-                total_sells: stats.total_sells,
-                total_buy_volume_token: stats.total_buy_volume_token.to_string(),
-                total_sell_volume_token: stats.total_sell_volume_token.to_string(),
-                total_buy_volume_usd: stats.total_buy_volume_usd.to_string(),
-                total_sell_volume_usd: stats.total_sell_volume_usd.to_string(),
-                total_volume_usd: total_volume.to_string(),
-                net_volume_token: if net_volume >= Decimal::ZERO {
-                    format!("+{}", net_volume)
-                } else {
-                    net_volume.to_string()
-                },
-                buy_sell_ratio,
-            }
+    // Rank, summarize the whole population, then convert to API format
+    let ranked = rank_traders(trader_stats, sort_by, payload.order.unwrap_or_default());
+    let summary = SummaryStats::from_traders(&ranked);
+    let traders = ranked
+        .into_iter()
+        .enumerate()
+        .map(|(i, stats)| TraderStatsAPI {
+            rank: Some(i + 1),
+            ..TraderStatsAPI::from_stats(stats, &labels)
         })
         .collect();
 
-    // Sort by total volume
-    traders.sort_by(|a, b| {
-        let a_vol: f64 = a.total_volume_usd.parse().unwrap_or(0.0);
-        let b_vol: f64 = b.total_volume_usd.parse().unwrap_or(0.0);
-        b_vol
-            .partial_cmp(&a_vol)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
+    Ok(RankedLeaderboard {
+        traders,
+        summary,
+        window,
+    })
+}
 
-    // Apply limit
-    let limit = payload.limit.unwrap_or(20);
-    traders.truncate(limit);
+/// Where the request's token is fetched from: its address on the requested
+/// network, or on each of `networks` via the token map.
+fn request_chains(
+    config: &Config,
+    payload: &LeaderboardRequest,
+    window: &SwapWindow,
+) -> Result<Vec<ChainToken>, ApiError> {
+    let Some(token) = &payload.token_address else {
+        return Err(ApiError::invalid_request(
+            "token_address is required unless demo is set",
+        ));
+    };
 
-    // Calculate summary
-    let total_traders = traders.len();
-    let total_volume: f64 = traders
-        .iter()
-        .map(|t| t.total_volume_usd.parse().unwrap_or(0.0))
-        .sum();
-    let total_buys: u32 = traders.iter().map(|t| t.total_buys).sum();
-    let total_sells: u32 = traders.iter().map(|t| t.total_sells).sum();
+    let Some(networks) = &payload.networks else {
+        validate_address(token, "token")?;
+        return Ok(vec![ChainToken {
+            network: network_config(
+                &config.networks,
+                payload.network.as_deref().unwrap_or("ethereum"),
+            )?
+            .clone(),
+            address: token.to_lowercase(),
+        }]);
+    };
 
-    let response = LeaderboardResponse {
-        traders,
-        summary: SummaryStats {
-            total_traders,
-            total_volume_usd: format!("{:.2}", total_volume),
-            total_buy_transactions: total_buys,
-            total_sell_transactions: total_sells,
-            average_volume_per_trader: if total_traders > 0 {
-                format!("{:.2}", total_volume / total_traders as f64)
-            } else {
-                "0.00".to_string()
-            },
-        },
+    if window.start_block.is_some() || window.end_block.is_some() {
+        return Err(ApiError::invalid_request(
+            "start_block and end_block only apply to a single network; use from_timestamp and to_timestamp across networks",
+        ));
+    }
+    for network in networks.iter().filter(|network| *network != "all") {
+        network_config(&config.networks, network)?;
+    }
+    let token_map = TokenMap::load(config.token_map_path.as_deref(), &config.networks)
+        .map_err(|e| ApiError::internal("Failed to load token map", e))?;
+    token_map
+        .resolve(token, networks, &config.networks)
+        .map_err(|e| {
+            ApiError::invalid_request("Token could not be resolved on the requested networks")
+                .with_details(e)
+        })
+}
+
+fn network_config<'a>(
+    networks: &'a NetworkRegistry,
+    network: &str,
+) -> Result<&'a NetworkConfig, ApiError> {
+    networks.get(network).map_err(|e| {
+        ApiError::new(
+            ErrorCode::UnsupportedNetwork,
+            format!("Unsupported network: {}", network),
+        )
+        .with_details(e)
+    })
+}
+
+fn validate_address(address: &str, kind: &str) -> Result<(), ApiError> {
+    if !is_valid_address(address) {
+        return Err(ApiError::new(
+            ErrorCode::InvalidAddress,
+            format!(
+                "Invalid {} address format. Expected 42-character hex string starting with '0x'",
+                kind
+            ),
+        )
+        .with_details(address));
+    }
+    Ok(())
+}
+
+/// Swaps behind a response: either a watched token's warm set or a fresh fetch.
+enum LoadedSwaps {
+    Warm(Arc<WarmSwaps>),
+    Fetched(Vec<Swap>),
+}
+
+impl LoadedSwaps {
+    fn as_slice(&self) -> &[Swap] {
+        match self {
+            Self::Warm(warm) => &warm.swaps,
+            Self::Fetched(swaps) => swaps,
+        }
+    }
+}
+
+/// Loads swaps for `token` inside `window` from the pools selected by `pools`.
+/// Watched tokens have their default window refreshed in the background, so
+/// requests without explicit bounds or a pool filter skip the fetch; `window`
+/// is updated to the bounds actually used.
+async fn load_swaps(
+    state: &AppState,
+    config: &Config,
+    network_config: &NetworkConfig,
+    token: &str,
+    window: &mut SwapWindow,
+    pools: &PoolFilter,
+) -> Result<LoadedSwaps, ApiError> {
+    let network = &network_config.id;
+    let warm = if *window == SwapWindow::default() && pools.is_empty() {
+        WatchedToken::new(&config.networks, network, token)
+            .ok()
+            .and_then(|watched| state.watchlist.warm(&watched))
+    } else {
+        None
+    };
+
+    if let Some(warm) = warm {
+        println!(
+            "Serving {} swaps for token {} from the watchlist",
+            warm.swaps.len(),
+            token
+        );
+        *window = warm.window.clone();
+        return Ok(LoadedSwaps::Warm(warm));
+    }
+
+    fetch_swaps(
+        state,
+        network,
+        network_config.default_start_block_offset(),
+        token,
+        window,
+        pools,
+    )
+    .await
+    .map(LoadedSwaps::Fetched)
+}
+
+/// Fetches swaps for `token` from the configured source, filling in the
+/// default lookback start block when `window` has no bounds of its own.
+async fn fetch_swaps(
+    state: &AppState,
+    network: &str,
+    lookback_blocks: u64,
+    token: &str,
+    window: &mut SwapWindow,
+    pools: &PoolFilter,
+) -> Result<Vec<Swap>, ApiError> {
+    let config = &state.config;
+    let source = match build_swap_source(
+        config.swap_source,
+        network,
+        config,
+        state.swap_store.as_ref(),
+    ) {
+        Ok(source) => source,
+        Err(e) => return Err(ApiError::source_unavailable(network, &e)),
     };
 
-    Ok(Json(response))
+    // A time window replaces the default 30-day block lookback
+    if window.start_block.is_none() && !window.has_time_bounds() {
+        match source.default_start_block(lookback_blocks).await {
+            Ok(block) => window.start_block = Some(block),
+            Err(e) => return Err(ApiError::upstream(&e)),
+        }
+    }
+
+    println!("Fetching swaps for token: {}", token);
+    source.fetch_swaps(token, window, pools).await.map_err(|e| {
+        eprintln!("Error fetching swaps for token {}: {}", token, e);
+        ApiError::upstream(&e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::ResponseCache;
+    use crate::types::TraderStats;
+
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+
+    #[test]
+    fn test_windows_with_inverted_bounds_are_rejected() {
+        let window = parse_window(Some(100), None, Some("2024-05-01".to_string()), None).unwrap();
+        assert_eq!(window.start_block, Some(100));
+        assert_eq!(window.from_timestamp, Some(1_714_521_600));
+
+        let inverted_blocks = parse_window(Some(200), Some(100), None, None).unwrap_err();
+        assert_eq!(inverted_blocks.code, ErrorCode::InvalidRequest);
+        let inverted_times = parse_window(
+            None,
+            None,
+            Some("2024-05-02".to_string()),
+            Some("2024-05-01".to_string()),
+        );
+        assert!(inverted_times.is_err());
+    }
+
+    #[test]
+    fn test_query_parameters_map_onto_leaderboard_request() {
+        let now = chrono::DateTime::from_timestamp(1_714_608_000, 0).unwrap();
+        let uri = "/?from=1d&to=2024-05-01&limit=5&sort_by=net_flow&exclude=mev_bot,%20router"
+            .parse()
+            .unwrap();
+        let Query(query) = Query::<LeaderboardQuery>::try_from_uri(&uri).unwrap();
+
+        let request = query_to_request("base".into(), "0xABC".into(), query, now).unwrap();
+        assert_eq!(request.network.as_deref(), Some("base"));
+        assert_eq!(request.from_timestamp, Some(1_714_521_600));
+        assert_eq!(request.to_timestamp, Some(1_714_607_999));
+        assert_eq!(request.sort_by, Some(SortKey::NetFlow));
+        assert_eq!(
+            request.exclude_categories,
+            Some(vec![AddressCategory::MevBot, AddressCategory::Router])
+        );
+
+        let bad = LeaderboardQuery {
+            exclude: Some("whales".to_string()),
+            ..Default::default()
+        };
+        let error = query_to_request("base".into(), "0xabc".into(), bad, now).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidRequest);
+    }
+
+    #[tokio::test]
+    async fn test_relative_windows_share_a_cache_entry_within_the_ttl() {
+        let ttl = std::time::Duration::from_secs(60);
+        let networks = NetworkRegistry::load(None).unwrap();
+        let key_at = |seconds: i64| {
+            let now = chrono::DateTime::from_timestamp(seconds, 0).unwrap();
+            let uri = "/?from=7d&to=24h&limit=5".parse().unwrap();
+            let Query(query) = Query::<LeaderboardQuery>::try_from_uri(&uri).unwrap();
+            let request =
+                query_to_request("ethereum".into(), WETH.into(), query, cache_epoch(now, ttl))
+                    .unwrap();
+            ranking_request(&normalize_request(request, &networks))
+                .unwrap()
+                .1
+        };
+
+        let cache: ResponseCache<u32> = ResponseCache::new(ttl);
+        let computed = std::sync::atomic::AtomicU32::new(0);
+        for seconds in [1_714_608_010, 1_714_608_015] {
+            cache
+                .get_or_try_insert(key_at(seconds), || async {
+                    Ok::<_, ApiError>(computed.fetch_add(1, std::sync::atomic::Ordering::SeqCst))
+                })
+                .await
+                .unwrap();
+        }
+        assert_eq!(computed.into_inner(), 1);
+        assert_ne!(key_at(1_714_608_010), key_at(1_714_608_070));
+    }
+
+    #[test]
+    fn test_pages_resume_from_cursor_after_ranks_shift() {
+        let labels = LabelRegistry::default();
+        let ranked = |addresses: &[&str]| RankedLeaderboard {
+            traders: addresses
+                .iter()
+                .map(|address| {
+                    TraderStatsAPI::from_stats(TraderStats::new(address.to_string()), &labels)
+                })
+                .collect(),
+            summary: SummaryStats {
+                total_traders: addresses.len(),
+                total_volume_usd: "0.00".to_string(),
+                total_buy_transactions: 0,
+                total_sell_transactions: 0,
+                average_volume_per_trader: "0.00".to_string(),
+            },
+            window: SwapWindow::default(),
+        };
+
+        let leaderboard = ranked(&["0xa", "0xb", "0xc", "0xd", "0xe"]);
+        let first = leaderboard.page(0, 2);
+        assert_eq!(first.traders.len(), 2);
+        assert_eq!(first.summary.total_traders, 5);
+        assert_eq!(first.page.next_offset, Some(2));
+        let cursor = first.page.next_cursor.unwrap();
+        assert_eq!(leaderboard.resume_offset(&cursor).unwrap(), 2);
+
+        // 0xa dropped out, so the page after 0xb now starts one rank earlier
+        assert_eq!(
+            ranked(&["0xb", "0xc", "0xd", "0xe"])
+                .resume_offset(&cursor)
+                .unwrap(),
+            1
+        );
+        // Without 0xb the recorded offset is used
+        assert_eq!(
+            ranked(&["0xc", "0xd", "0xe"])
+                .resume_offset(&cursor)
+                .unwrap(),
+            2
+        );
+
+        let last = leaderboard.page(4, 2);
+        assert_eq!(last.traders.len(), 1);
+        assert!(last.page.next_offset.is_none() && last.page.next_cursor.is_none());
+        assert!(leaderboard.page(10, 2).traders.is_empty());
+        assert!(leaderboard.resume_offset("not-a-cursor").is_err());
+    }
 }
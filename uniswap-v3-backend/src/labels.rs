@@ -0,0 +1,181 @@
+use anyhow::{Result, anyhow};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::types::TraderStats;
+use crate::utils::{is_valid_address, load_registry_file, parse_toml};
+
+/// Known addresses shipped with the binary
+const BUNDLED_LABELS: &str = include_str!("../labels/known_addresses.toml");
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum AddressCategory {
+    Router,
+    Aggregator,
+    MevBot,
+    Cex,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressLabel {
+    pub label: String,
+    pub category: AddressCategory,
+}
+
+#[derive(Debug, Deserialize)]
+struct LabelFile {
+    #[serde(default)]
+    labels: Vec<LabelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LabelEntry {
+    address: String,
+    label: String,
+    category: AddressCategory,
+    /// Networks the entry applies to; empty means all of them
+    #[serde(default)]
+    networks: Vec<String>,
+}
+
+/// Address labels for one network, built from the bundled registry and an
+/// optional user file whose entries override the bundled ones.
+#[derive(Debug, Default)]
+pub struct LabelRegistry {
+    labels: HashMap<String, AddressLabel>,
+}
+
+impl LabelRegistry {
+    /// `network` is the canonical network id (see `NetworkConfig::id`).
+    pub fn load(network: &str, user_file: Option<&str>) -> Result<Self> {
+        Self::load_networks(&[network], user_file)
+    }
+
+    /// Labels that apply on any of `networks`, for leaderboards spanning
+    /// several of them.
+    pub fn load_networks(networks: &[&str], user_file: Option<&str>) -> Result<Self> {
+        let mut registry = Self::default();
+        registry.merge(parse_toml(BUNDLED_LABELS, "bundled labels")?, networks)?;
+
+        if let Some(path) = user_file {
+            registry.merge(load_registry_file(Path::new(path), "label file")?, networks)?;
+        }
+
+        Ok(registry)
+    }
+
+    pub fn get(&self, address: &str) -> Option<&AddressLabel> {
+        self.labels.get(&address.to_lowercase())
+    }
+
+    pub fn is_excluded(&self, address: &str, excluded: &[AddressCategory]) -> bool {
+        self.get(address)
+            .is_some_and(|label| excluded.contains(&label.category))
+    }
+
+    /// Drops traders whose label falls in one of the `excluded` categories.
+    pub fn exclude_categories(
+        &self,
+        trader_stats: &mut HashMap<String, TraderStats>,
+        excluded: &[AddressCategory],
+    ) {
+        if excluded.is_empty() {
+            return;
+        }
+        let before = trader_stats.len();
+        trader_stats.retain(|address, _| !self.is_excluded(address, excluded));
+        eprintln!(
+            "Excluded {} labeled traders in categories {:?}",
+            before - trader_stats.len(),
+            excluded
+        );
+    }
+
+    fn merge(&mut self, file: LabelFile, networks: &[&str]) -> Result<()> {
+        for entry in file.labels {
+            if !entry.networks.is_empty()
+                && !entry.networks.iter().any(|n| {
+                    networks
+                        .iter()
+                        .any(|network| n.eq_ignore_ascii_case(network))
+                })
+            {
+                continue;
+            }
+
+            let address = entry.address.to_lowercase();
+            if !is_valid_address(&address) {
+                return Err(anyhow!(
+                    "Invalid address '{}' in label registry",
+                    entry.address
+                ));
+            }
+
+            self.labels.insert(
+                address,
+                AddressLabel {
+                    label: entry.label,
+                    category: entry.category,
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SWAP_ROUTER_02: &str = "0x68b3465833fb72a70ecdf485e0e4c7bd8665fc45";
+
+    #[test]
+    fn test_bundled_labels_are_network_scoped() {
+        let ethereum = LabelRegistry::load("ethereum", None).unwrap();
+        let router = ethereum.get(SWAP_ROUTER_02).unwrap();
+        assert_eq!(router.category, AddressCategory::Router);
+        assert!(ethereum.is_excluded(
+            "0x6B75D8AF000000E20B7A7DDF000BA900B4009A80",
+            &[AddressCategory::MevBot]
+        ));
+
+        let base = LabelRegistry::load("base", None).unwrap();
+        assert!(base.get(SWAP_ROUTER_02).is_none());
+    }
+
+    #[test]
+    fn test_user_labels_override_bundled() {
+        let mut registry = LabelRegistry::load("ethereum", None).unwrap();
+        let user_file = parse_toml(
+            r#"
+            [[labels]]
+            address = "0x68B3465833FB72A70ECDF485E0E4C7BD8665FC45"
+            label = "Our router"
+            category = "other"
+
+            [[labels]]
+            address = "0x1111111111111111111111111111111111111111"
+            label = "Desk wallet"
+            category = "other"
+            networks = ["arbitrum"]
+            "#,
+            "test labels",
+        )
+        .unwrap();
+        registry.merge(user_file, &["ethereum"]).unwrap();
+
+        assert_eq!(registry.get(SWAP_ROUTER_02).unwrap().label, "Our router");
+        assert!(
+            registry
+                .get("0x1111111111111111111111111111111111111111")
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::refresher::{WarmSwaps, WatchedToken};
+use crate::types::{AggregationOptions, SortKey, SortOrder, Swap, TraderStats};
+use crate::utils::{determine_trade_type, rank_traders};
+
+/// How many leading ranks live clients are told about
+pub const LIVE_RANKS: usize = 20;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RankedTrader {
+    pub rank: usize,
+    pub address: String,
+    pub total_volume_usd: String,
+    pub total_buys: u32,
+    pub total_sells: u32,
+}
+
+/// Current top of the leaderboard, sent when a client subscribes.
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveSnapshot {
+    pub network: String,
+    pub token_address: String,
+    pub refreshed_at: u64,
+    pub traders: Vec<RankedTrader>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveSwap {
+    pub id: String,
+    pub timestamp: String,
+    pub trader: String,
+    pub is_buy: bool,
+    pub amount_token: String,
+    pub amount_usd: String,
+}
+
+/// A trader whose position in the top `LIVE_RANKS` changed. `None` ranks
+/// mean the trader entered or left the top.
+#[derive(Debug, Clone, Serialize)]
+pub struct RankChange {
+    pub address: String,
+    pub previous_rank: Option<usize>,
+    pub rank: Option<usize>,
+    pub total_volume_usd: String,
+}
+
+/// What changed between two refreshes of a watched token.
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveUpdate {
+    pub network: String,
+    pub token_address: String,
+    pub refreshed_at: u64,
+    pub new_swaps: Vec<LiveSwap>,
+    pub rank_changes: Vec<RankChange>,
+}
+
+pub fn snapshot(token: &WatchedToken, warm: &WarmSwaps) -> LiveSnapshot {
+    LiveSnapshot {
+        network: token.network.clone(),
+        token_address: token.token_address.clone(),
+        refreshed_at: warm.refreshed_at,
+        traders: top_traders(warm)
+            .into_iter()
+            .enumerate()
+            .map(|(i, trader)| RankedTrader {
+                rank: i + 1,
+                address: trader.address.clone(),
+                total_volume_usd: trader.total_volume_usd().to_string(),
+                total_buys: trader.total_buys,
+                total_sells: trader.total_sells,
+            })
+            .collect(),
+    }
+}
+
+/// Diffs two refreshes, returning `None` when nothing a client shows changed.
+pub fn diff(token: &WatchedToken, previous: &WarmSwaps, current: &WarmSwaps) -> Option<LiveUpdate> {
+    let target = &token.token_address;
+    let seen: HashSet<&str> = previous.swaps.iter().map(|swap| swap.id.as_str()).collect();
+    let options = AggregationOptions::default();
+
+    let mut new_swaps: Vec<LiveSwap> = current
+        .swaps
+        .iter()
+        .filter(|swap| !seen.contains(swap.id.as_str()))
+        .filter_map(|swap| live_swap(swap, target, &options))
+        .collect();
+    new_swaps.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.id.cmp(&b.id)));
+
+    let before = rank_map(&top_traders(previous));
+    let after_traders = top_traders(current);
+    let after = rank_map(&after_traders);
+
+    let mut rank_changes: Vec<RankChange> = after_traders
+        .iter()
+        .filter(|trader| before.get(&trader.address) != after.get(&trader.address))
+        .map(|trader| RankChange {
+            address: trader.address.clone(),
+            previous_rank: before.get(&trader.address).copied(),
+            rank: after.get(&trader.address).copied(),
+            total_volume_usd: trader.total_volume_usd().to_string(),
+        })
+        .collect();
+    let mut dropped: Vec<&String> = before
+        .keys()
+        .filter(|address| !after.contains_key(*address))
+        .collect();
+    dropped.sort();
+    rank_changes.extend(dropped.into_iter().map(|address| RankChange {
+        address: address.clone(),
+        previous_rank: before.get(address).copied(),
+        rank: None,
+        total_volume_usd: String::new(),
+    }));
+
+    if new_swaps.is_empty() && rank_changes.is_empty() {
+        return None;
+    }
+    Some(LiveUpdate {
+        network: token.network.clone(),
+        token_address: token.token_address.clone(),
+        refreshed_at: current.refreshed_at,
+        new_swaps,
+        rank_changes,
+    })
+}
+
+fn top_traders(warm: &WarmSwaps) -> Vec<TraderStats> {
+    let mut ranked = rank_traders(warm.trader_stats.clone(), SortKey::Volume, SortOrder::Desc);
+    ranked.truncate(LIVE_RANKS);
+    ranked
+}
+
+fn rank_map(traders: &[TraderStats]) -> HashMap<String, usize> {
+    traders
+        .iter()
+        .enumerate()
+        .map(|(i, trader)| (trader.address.clone(), i + 1))
+        .collect()
+}
+
+fn live_swap(swap: &Swap, target: &str, options: &AggregationOptions) -> Option<LiveSwap> {
+    let (is_buy, amount_token, amount_usd) = determine_trade_type(swap, target).ok()?;
+    let trader = options.trader_address(swap)?;
+    Some(LiveSwap {
+        id: swap.id.clone(),
+        timestamp: swap.timestamp.clone(),
+        trader: trader.to_lowercase(),
+        is_buy,
+        amount_token: amount_token.to_string(),
+        amount_usd: amount_usd.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SwapWindow;
+
+    #[test]
+    fn test_diff_reports_new_swaps_and_rank_moves() {
+        let swaps: Vec<Swap> =
+            serde_json::from_str(include_str!("../fixtures/swaps.json")).unwrap();
+        let token = WatchedToken {
+            network: "ethereum".to_string(),
+            token_address: "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".to_string(),
+        };
+        let warm = |swaps: Vec<Swap>, refreshed_at| {
+            WarmSwaps::new(
+                &token.token_address,
+                swaps,
+                SwapWindow::default(),
+                refreshed_at,
+            )
+            .unwrap()
+        };
+
+        // Only A and B had traded before swap 0x04; C enters with 0x05
+        let earlier: Vec<Swap> = swaps
+            .iter()
+            .filter(|swap| swap.id.as_str() < "0x04")
+            .cloned()
+            .collect();
+        let previous = warm(earlier, 1);
+        let current = warm(swaps, 2);
+
+        let update = diff(&token, &previous, &current).unwrap();
+        let new_ids: Vec<&str> = update.new_swaps.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(new_ids, vec!["0x04#2", "0x05#0", "0x06#3"]);
+
+        assert_eq!(update.rank_changes.len(), 1);
+        let c = &update.rank_changes[0];
+        assert!(c.address.starts_with("0x3333"));
+        assert_eq!((c.previous_rank, c.rank), (None, Some(3)));
+        assert!(diff(&token, &current, &current).is_none());
+    }
+}
@@ -1,25 +1,56 @@
+mod cache;
 mod client;
 mod config;
+mod errors;
 mod handlers;
+mod labels;
+mod live;
+mod multichain;
+mod networks;
+mod output;
+mod pnl;
+mod pools;
+mod refresher;
 mod server;
+mod sources;
+mod store;
+mod tokens;
+mod trader;
 mod types;
 mod utils;
 
 use anyhow::{Result, anyhow};
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 
-use crate::client::UniswapClient;
-use crate::config::Config;
+use crate::client::ClientError;
+use crate::config::{Config, ConfigSources};
+use crate::labels::{AddressCategory, LabelRegistry};
+use crate::multichain::{merge_network_stats, network_list};
+use crate::output::{OutputFormat, write_leaderboard, write_pools, write_trader};
+use crate::pnl::CostBasisMode;
+use crate::pools::{aggregate_pool_stats, rank_pools};
 use crate::server::run_server;
+use crate::sources::{SourceKind, build_swap_source, open_swap_store};
+use crate::tokens::{ChainToken, TokenMap};
+use crate::trader::trader_history;
+use crate::types::{
+    AggregationOptions, AttributionMode, PoolFilter, PoolSortKey, SortKey, SortOrder, Swap,
+    SwapWindow, TraderStats,
+};
 use crate::utils::{
-    aggregate_trader_stats, generate_demo_data, get_default_start_block, print_leaderboard,
+    TimeBound, aggregate_trader_stats, format_timestamp, generate_demo_data, is_valid_address,
+    parse_time_bound, rank_traders,
 };
 
 #[derive(Parser, Debug)]
 #[command(name = "uni-leaderboard")]
 #[command(about = "Fetches Uniswap v3 swap data and creates trader leaderboards")]
 struct Args {
-    /// Token contract address (ERC20) - not required in demo mode
+    /// Token contract address (ERC20) - not required in demo mode. With several
+    /// networks this may also be a symbol from the token map (e.g. WETH)
     #[arg(short, long)]
     token: Option<String>,
 
@@ -31,6 +62,14 @@ struct Args {
     #[arg(short, long)]
     end_block: Option<u64>,
 
+    /// Only include swaps at or after this time (2024-05-01, RFC 3339, unix seconds, or relative like 7d/24h)
+    #[arg(long)]
+    since: Option<String>,
+
+    /// Only include swaps at or before this time (same formats as --since; a bare date includes the whole day)
+    #[arg(long)]
+    until: Option<String>,
+
     /// Maximum number of traders to display in leaderboard
     #[arg(short, long)]
     limit: Option<usize>,
@@ -47,17 +86,115 @@ struct Args {
     #[arg(long)]
     port: Option<u16>,
 
-    /// Network to query (ethereum, arbitrum, polygon, optimism, base)
+    /// Network to query (ethereum, arbitrum, polygon, optimism, base, or any added in NETWORKS_PATH), a comma-separated list, or all
     #[arg(long, default_value = "ethereum")]
     network: String,
+
+    /// Extra per-network token addresses (TOML or JSON) merged over the bundled map (defaults to TOKEN_MAP_PATH)
+    #[arg(long)]
+    token_map: Option<String>,
+
+    /// Address each trade is credited to: origin (tx sender EOA), recipient, or sender (often a router)
+    #[arg(long, value_enum, default_value_t = AttributionMode::Origin)]
+    attribution: AttributionMode,
+
+    /// Address used when the --attribution address is unavailable
+    #[arg(long, value_enum, default_value_t = AttributionMode::Recipient)]
+    attribution_fallback: AttributionMode,
+
+    /// Compute realized and unrealized PnL per trader using this cost basis
+    #[arg(long, value_enum)]
+    pnl: Option<CostBasisMode>,
+
+    /// Metric traders are ranked by (ranking by pnl implies --pnl fifo)
+    #[arg(long, value_enum, default_value_t = SortKey::Volume)]
+    sort_by: SortKey,
+
+    /// Ranking direction; ties are always broken by address
+    #[arg(long, value_enum, default_value_t = SortOrder::Desc)]
+    order: SortOrder,
+
+    /// Only count swaps in these pools (comma-separated pool addresses)
+    #[arg(long = "pool", value_delimiter = ',')]
+    pools: Vec<String>,
+
+    /// Only count swaps in pools with these fee tiers, in hundredths of a basis point (e.g. 500,3000)
+    #[arg(long = "fee-tier", value_delimiter = ',')]
+    fee_tiers: Vec<u32>,
+
+    /// Split each trader's stats by pool, showing the pair and fee tier
+    #[arg(long)]
+    by_pool: bool,
+
+    /// Leave labeled addresses in these categories out of the ranking (e.g. router,mev_bot)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    exclude_category: Vec<AddressCategory>,
+
+    /// Extra address labels (TOML or JSON) merged over the bundled registry (defaults to ADDRESS_LABELS_PATH)
+    #[arg(long)]
+    labels: Option<String>,
+
+    /// Where swaps are read from (defaults to SWAP_SOURCE, or subgraph)
+    #[arg(long, value_enum)]
+    source: Option<SourceKind>,
+
+    /// JSON-RPC endpoint for the rpc source (defaults to RPC_URL)
+    #[arg(long)]
+    rpc_url: Option<String>,
+
+    /// JSON file of swaps for the fixture source (defaults to SWAP_FIXTURE_PATH)
+    #[arg(long)]
+    fixture: Option<String>,
+
+    /// SQLite file that keeps fetched swaps between runs (defaults to SWAP_STORE_PATH)
+    #[arg(long)]
+    store: Option<String>,
+
+    /// Output format; everything but table is meant for other programs
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table, global = true)]
+    format: OutputFormat,
+
+    /// Write results to this file instead of stdout (progress always goes to stderr)
+    #[arg(long, global = true)]
+    output: Option<String>,
+
+    /// TOML config file (defaults to LEADERBOARD_CONFIG, then ./leaderboard.toml if present)
+    #[arg(long, global = true)]
+    config: Option<String>,
+
+    /// Config file profile applied over its top-level settings (defaults to LEADERBOARD_PROFILE)
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show one trader's stats, per-pool breakdown and swap history for --token
+    Trader {
+        /// Trader address, credited the same way as on the leaderboard (see --attribution)
+        address: String,
+
+        /// Number of most recent swaps to skip; --limit sets how many are listed
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+    },
+    /// Rank the pools trading --token by volume, swaps, traders or TVL
+    Pools {
+        /// Metric pools are ranked by (--order sets the direction)
+        #[arg(long, value_enum, default_value_t = PoolSortKey::Volume)]
+        sort_by: PoolSortKey,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv::dotenv().ok();
 
-    let config = Config::from_env()?;
     let args = Args::parse();
+    let config = Config::load(&config_sources(&args))?;
 
     // Use config defaults for optional args
     let limit = args.limit.unwrap_or(config.default_limit);
@@ -65,18 +202,29 @@ async fn main() -> Result<()> {
 
     // If server mode, run HTTP server
     if args.server {
-        println!("🚀 Starting Uniswap v3 Leaderboard HTTP Server");
-        println!("Port: {}", port);
-        println!("API key: configured and ready");
-        println!();
-        return run_server(port).await;
+        eprintln!("🚀 Starting Uniswap v3 Leaderboard HTTP Server");
+        eprintln!("Port: {}", port);
+        if let Some(path) = &config.config_path {
+            match &config.profile {
+                Some(profile) => eprintln!("Config: {} (profile {})", path, profile),
+                None => eprintln!("Config: {}", path),
+            }
+        }
+        match &config.graph_api_key {
+            Some(_) => eprintln!("API key: configured"),
+            None => eprintln!("API key: not set (gateway subgraphs are unavailable)"),
+        }
+        eprintln!();
+        return run_server(port, &config).await;
     }
 
     // Validate arguments based on mode for CLI
+    let networks = network_list(&args.network);
     if !args.demo {
         match &args.token {
             Some(token) => {
-                if !token.starts_with("0x") || token.len() != 42 {
+                // Several networks resolve the token through the token map instead
+                if networks.is_none() && !is_valid_address(token) {
                     return Err(anyhow!(
                         "Invalid token address format. Expected 42-character hex string starting with '0x'"
                     ));
@@ -90,47 +238,191 @@ async fn main() -> Result<()> {
         }
     }
 
-    let start_block = args.start_block.unwrap_or_else(get_default_start_block);
-    let end_block = args.end_block;
+    let now = chrono::Utc::now();
+    let from_timestamp = args
+        .since
+        .as_deref()
+        .map(|since| parse_time_bound(since, TimeBound::Start, now))
+        .transpose()?;
+    let to_timestamp = args
+        .until
+        .as_deref()
+        .map(|until| parse_time_bound(until, TimeBound::End, now))
+        .transpose()?;
+    if let (Some(from), Some(to)) = (from_timestamp, to_timestamp)
+        && from > to
+    {
+        return Err(anyhow!("--since must not be later than --until"));
+    }
+    if let Some(pool) = args.pools.iter().find(|pool| !is_valid_address(pool)) {
+        return Err(anyhow!("Invalid pool address: {}", pool));
+    }
 
-    println!("🚀 Starting Uniswap v3 Trader Leaderboard Analysis");
+    eprintln!("🚀 Starting Uniswap v3 Trader Leaderboard Analysis");
     if let Some(token) = &args.token {
-        println!("Token Address: {}", token);
+        eprintln!("Token Address: {}", token);
     }
-    if !args.demo {
-        println!("Start Block: {}", start_block);
-        if let Some(end) = end_block {
-            println!("End Block: {}", end);
-        } else {
-            println!("End Block: Latest");
+    eprintln!("Leaderboard Limit: {}", limit);
+    eprintln!();
+
+    let chains = match &networks {
+        Some(networks) if !args.demo => {
+            if args.start_block.is_some() || args.end_block.is_some() {
+                return Err(anyhow!(
+                    "--start-block and --end-block only apply to a single network; use --since/--until across networks"
+                ));
+            }
+            let token = args.token.as_ref().unwrap(); // Safe because we validated above
+            TokenMap::load(config.token_map_path.as_deref(), &config.networks)?.resolve(
+                token,
+                networks,
+                &config.networks,
+            )?
+        }
+        _ => Vec::new(),
+    };
+    let labels = match &networks {
+        Some(_) => {
+            let ids: Vec<&str> = chains
+                .iter()
+                .map(|chain| chain.network.id.as_str())
+                .collect();
+            LabelRegistry::load_networks(&ids, config.labels_path.as_deref())?
+        }
+        None => LabelRegistry::load(
+            &config.networks.get(&args.network)?.id,
+            config.labels_path.as_deref(),
+        )?,
+    };
+
+    let options = AggregationOptions {
+        attribution: args.attribution,
+        attribution_fallback: args.attribution_fallback,
+        cost_basis: args
+            .pnl
+            .or((args.sort_by == SortKey::Pnl).then_some(CostBasisMode::Fifo)),
+        pool_breakdown: args.by_pool,
+    };
+
+    if let Some(Command::Trader { address, offset }) = &args.command {
+        if args.demo {
+            return Err(anyhow!(
+                "The trader command needs real swaps. Use --token <ADDRESS> instead of --demo."
+            ));
+        }
+        if networks.is_some() {
+            return Err(anyhow!(
+                "The trader command works on one --network at a time."
+            ));
+        }
+        let token = args.token.as_ref().unwrap(); // Safe because we validated above
+        let (swaps, window) = fetch_token_swaps(
+            &args,
+            &config,
+            &args.network,
+            token,
+            from_timestamp,
+            to_timestamp,
+        )
+        .await?;
+        return match trader_history(&swaps, token, address, &options)? {
+            Some(history) => write_output(args.output.as_deref(), |out| {
+                write_trader(out, args.format, &history, *offset, limit, &labels, &window)
+            }),
+            None => {
+                eprintln!(
+                    "⚠️  No swaps credited to {} in this window ({} swaps scanned).",
+                    address,
+                    swaps.len()
+                );
+                Ok(())
+            }
+        };
+    }
+
+    if let Some(Command::Pools { sort_by }) = &args.command {
+        if args.demo {
+            return Err(anyhow!(
+                "The pools command needs real swaps. Use --token <ADDRESS> instead of --demo."
+            ));
+        }
+        if networks.is_some() {
+            return Err(anyhow!(
+                "The pools command works on one --network at a time."
+            ));
         }
+        let token = args.token.as_ref().unwrap(); // Safe because we validated above
+        let (swaps, window) = fetch_token_swaps(
+            &args,
+            &config,
+            &args.network,
+            token,
+            from_timestamp,
+            to_timestamp,
+        )
+        .await?;
+        let pools = aggregate_pool_stats(&swaps, token, &options)?;
+        if pools.is_empty() {
+            eprintln!("⚠️  No pools traded the specified token in this window.");
+            return Ok(());
+        }
+        let pools = rank_pools(pools, *sort_by, args.order);
+        return write_output(args.output.as_deref(), |out| {
+            write_pools(out, args.format, &pools, limit, &window)
+        });
     }
-    println!("Leaderboard Limit: {}", limit);
-    println!();
 
-    let trader_stats = if args.demo {
-        println!("🎭 Running in DEMO mode with sample data");
-        println!("   (This demonstrates the tool's functionality when subgraph data is available)");
-        println!();
+    let mut window = SwapWindow::default();
+    let mut trader_stats = if args.demo {
+        eprintln!("🎭 Running in DEMO mode with sample data");
+        eprintln!(
+            "   (This demonstrates the tool's functionality when subgraph data is available)"
+        );
+        eprintln!();
         generate_demo_data()
+    } else if !chains.is_empty() {
+        // Block numbers differ per network, so only the time bounds are shared
+        window.from_timestamp = from_timestamp;
+        window.to_timestamp = to_timestamp;
+        let stats = aggregate_networks(
+            &args,
+            &config,
+            &chains,
+            &options,
+            from_timestamp,
+            to_timestamp,
+        )
+        .await?;
+
+        if stats.is_empty() {
+            eprintln!("⚠️  No swaps found for the specified token on any of the networks.");
+            return Ok(());
+        }
+
+        stats
     } else {
-        let client = UniswapClient::new(&args.network)?;
         let token = args.token.as_ref().unwrap(); // Safe because we validated above
-
-        let swaps = client
-            .fetch_all_swaps(token, Some(start_block), end_block)
-            .await?;
+        let swaps;
+        (swaps, window) = fetch_token_swaps(
+            &args,
+            &config,
+            &args.network,
+            token,
+            from_timestamp,
+            to_timestamp,
+        )
+        .await?;
 
         if swaps.is_empty() {
-            println!("⚠️  No swaps found for the specified token and block range.");
-            println!();
-            println!("💡 Possible reasons:");
-            println!("   • The subgraph endpoint requires an API key (see README for setup)");
-            println!("   • No trading activity in the specified block range");
-            println!("   • Token address is incorrect or not traded on Uniswap v3");
-            println!();
-            println!("🎭 Try running with --demo flag to see sample output:");
-            println!(
+            eprintln!("⚠️  No swaps found for the specified token and block range.");
+            eprintln!();
+            eprintln!("💡 Possible reasons:");
+            eprintln!("   • The subgraph endpoint requires an API key (see README for setup)");
+            eprintln!("   • No trading activity in the specified block range");
+            eprintln!("   • Token address is incorrect or not traded on Uniswap v3");
+            eprintln!();
+            eprintln!("🎭 Try running with --demo flag to see sample output:");
+            eprintln!(
                 "   {} --demo --limit 5",
                 std::env::args()
                     .next()
@@ -139,30 +431,173 @@ async fn main() -> Result<()> {
             return Ok(());
         }
 
-        let stats = aggregate_trader_stats(&swaps, token)?;
+        let stats = aggregate_trader_stats(&swaps, token, &options)?;
 
         if stats.is_empty() {
-            println!("⚠️  No valid trader statistics could be calculated.");
+            eprintln!("⚠️  No valid trader statistics could be calculated.");
             return Ok(());
         }
 
         stats
     };
 
-    print_leaderboard(trader_stats, limit);
+    labels.exclude_categories(&mut trader_stats, &args.exclude_category);
+    let traders = rank_traders(trader_stats, args.sort_by, args.order);
+    write_output(args.output.as_deref(), |out| {
+        write_leaderboard(out, args.format, &traders, limit, &labels, &window)
+    })
+}
 
+/// Config file, profile and the CLI flags that override configured settings.
+fn config_sources(args: &Args) -> ConfigSources {
+    let mut overrides = Vec::new();
+    if let Some(source) = args.source.and_then(|source| source.to_possible_value()) {
+        overrides.push(("swap_source", source.get_name().to_string()));
+    }
+    let paths = [
+        ("rpc_url", &args.rpc_url),
+        ("fixture_path", &args.fixture),
+        ("labels_path", &args.labels),
+        ("store_path", &args.store),
+        ("token_map_path", &args.token_map),
+    ];
+    for (key, value) in paths {
+        if let Some(value) = value {
+            overrides.push((key, value.clone()));
+        }
+    }
+
+    ConfigSources {
+        path: args.config.clone(),
+        profile: args.profile.clone(),
+        overrides,
+    }
+}
+
+/// Runs `write` against `path`, or stdout when no path is given.
+fn write_output(
+    path: Option<&str>,
+    write: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    match path {
+        Some(path) => {
+            let file = File::create(path)
+                .map_err(|e| anyhow!("Failed to create output file {}: {}", path, e))?;
+            let mut out = BufWriter::new(file);
+            write(&mut out)?;
+            out.flush()?;
+            eprintln!("📝 Wrote results to {}", path);
+        }
+        None => {
+            let mut out = io::stdout().lock();
+            write(&mut out)?;
+            out.flush()?;
+        }
+    }
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Fetches the token on every chain in `chains` concurrently and combines the
+/// traders into one population with a per-network breakdown.
+async fn aggregate_networks(
+    args: &Args,
+    config: &Config,
+    chains: &[ChainToken],
+    options: &AggregationOptions,
+    from_timestamp: Option<u64>,
+    to_timestamp: Option<u64>,
+) -> Result<HashMap<String, TraderStats>> {
+    let fetched = futures::future::try_join_all(chains.iter().map(|chain| async move {
+        fetch_token_swaps(
+            args,
+            config,
+            &chain.network.id,
+            &chain.address,
+            from_timestamp,
+            to_timestamp,
+        )
+        .await
+        .map_err(|e| anyhow!("{}: {}", chain.network.name, e))
+    }))
+    .await?;
+
+    let mut per_network = Vec::new();
+    for (chain, (swaps, _)) in chains.iter().zip(fetched) {
+        eprintln!("{}: {} swaps", chain.network.name, swaps.len());
+        per_network.push((
+            chain.network.id.as_str(),
+            aggregate_trader_stats(&swaps, &chain.address, options)?,
+        ));
+    }
+    Ok(merge_network_stats(per_network))
+}
 
-    #[test]
-    fn test_config_from_env() {
-        let config = Config::from_env().unwrap();
-        assert!(!config.graph_api_key.is_empty());
-        assert!(config.default_port > 0);
-        assert!(config.default_limit > 0);
+/// Fetches swaps for `token` on `network` over the window given on the
+/// command line, defaulting to the network's lookback when no bounds were
+/// passed, and returns them with the window actually used.
+async fn fetch_token_swaps(
+    args: &Args,
+    config: &Config,
+    network: &str,
+    token: &str,
+    from_timestamp: Option<u64>,
+    to_timestamp: Option<u64>,
+) -> Result<(Vec<Swap>, SwapWindow)> {
+    let network_config = config.networks.get(network)?;
+    let store = open_swap_store(config)?;
+    let source = build_swap_source(config.swap_source, network, config, store.as_ref())?;
+
+    // A time window replaces the default 30-day block lookback
+    let start_block = match args.start_block {
+        Some(block) => Some(block),
+        None if from_timestamp.is_some() || to_timestamp.is_some() => None,
+        None => Some(
+            source
+                .default_start_block(network_config.default_start_block_offset())
+                .await?,
+        ),
+    };
+    let window = SwapWindow {
+        start_block,
+        end_block: args.end_block,
+        from_timestamp,
+        to_timestamp,
+    };
+
+    eprintln!("Network: {}", network_config.name);
+    if let Some(start) = window.start_block {
+        eprintln!("Start Block: {}", start);
+    }
+    if let Some(end) = window.end_block {
+        eprintln!("End Block: {}", end);
+    } else if window.start_block.is_some() {
+        eprintln!("End Block: Latest");
+    }
+    if let Some(from) = window.from_timestamp {
+        eprintln!("Since: {}", format_timestamp(from));
+    }
+    if let Some(to) = window.to_timestamp {
+        eprintln!("Until: {}", format_timestamp(to));
+    }
+    eprintln!();
+
+    eprintln!("Swap source: {}", source.name());
+    let pools = PoolFilter::new(&args.pools, &args.fee_tiers);
+    match source.fetch_swaps(token, &window, &pools).await {
+        Ok(swaps) => Ok((swaps, window)),
+        Err(e) => {
+            if let Some(ClientError::Auth(_) | ClientError::SubgraphNotFound(_)) =
+                e.downcast_ref::<ClientError>()
+            {
+                eprintln!("💡 Check GRAPH_API_KEY and the subgraph id, or try demo mode instead:");
+                eprintln!(
+                    "   {} --demo --limit 10",
+                    std::env::args()
+                        .next()
+                        .unwrap_or("uni-leaderboard".to_string())
+                );
+            }
+            Err(e)
+        }
     }
 }
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use crate::trader::sort_pools;
+use crate::types::{NetworkActivity, TraderStats};
+
+/// Splits a network argument naming several networks (`all`, or a comma
+/// list like `ethereum,base`). Returns `None` for a single network.
+pub fn network_list(spec: &str) -> Option<Vec<String>> {
+    if !spec.eq_ignore_ascii_case("all") && !spec.contains(',') {
+        return None;
+    }
+    Some(
+        spec.split(',')
+            .map(|network| network.trim().to_lowercase())
+            .filter(|network| !network.is_empty())
+            .collect(),
+    )
+}
+
+/// Combines per-network stats into one entry per trader, keeping each
+/// network's buys, sells and volume in `TraderStats::networks`.
+pub fn merge_network_stats(
+    per_network: Vec<(&str, HashMap<String, TraderStats>)>,
+) -> HashMap<String, TraderStats> {
+    let mut combined: HashMap<String, TraderStats> = HashMap::new();
+
+    for (network, stats) in per_network {
+        for (address, stats) in stats {
+            let activity = NetworkActivity {
+                total_buys: stats.total_buys,
+                total_sells: stats.total_sells,
+                total_volume_usd: stats.total_volume_usd(),
+            };
+            let trader = combined
+                .entry(address.clone())
+                .or_insert_with(|| TraderStats::new(address));
+
+            trader.total_buys += stats.total_buys;
+            trader.total_sells += stats.total_sells;
+            trader.total_buy_volume_token += stats.total_buy_volume_token;
+            trader.total_sell_volume_token += stats.total_sell_volume_token;
+            trader.total_buy_volume_usd += stats.total_buy_volume_usd;
+            trader.total_sell_volume_usd += stats.total_sell_volume_usd;
+            trader.pnl = match (trader.pnl.take(), stats.pnl) {
+                (Some(mut pnl), Some(other)) => {
+                    pnl.merge(&other);
+                    Some(pnl)
+                }
+                (pnl, other) => pnl.or(other),
+            };
+            trader.networks.insert(network.to_string(), activity);
+            trader.pools.extend(stats.pools);
+        }
+    }
+
+    for trader in combined.values_mut() {
+        sort_pools(&mut trader.pools);
+    }
+    combined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pnl::TraderPnl;
+    use rust_decimal::Decimal;
+
+    fn stats(address: &str, buys: u32, volume: i64, pnl: Option<TraderPnl>) -> TraderStats {
+        TraderStats {
+            total_buys: buys,
+            total_buy_volume_token: Decimal::from(buys),
+            total_buy_volume_usd: Decimal::from(volume),
+            pnl,
+            ..TraderStats::new(address.to_string())
+        }
+    }
+
+    fn pnl(position: i64, entry: i64, unrealized: Option<i64>) -> TraderPnl {
+        TraderPnl {
+            realized_pnl_usd: Decimal::from(10),
+            position_token: Decimal::from(position),
+            average_entry_price_usd: Some(Decimal::from(entry)),
+            unrealized_pnl_usd: unrealized.map(Decimal::from),
+        }
+    }
+
+    #[test]
+    fn test_traders_are_combined_across_networks() {
+        assert_eq!(network_list("ethereum"), None);
+        assert_eq!(
+            network_list("Ethereum, base"),
+            Some(vec!["ethereum".to_string(), "base".to_string()])
+        );
+        assert_eq!(network_list("all"), Some(vec!["all".to_string()]));
+
+        let ethereum = HashMap::from([
+            (
+                "0xaa".to_string(),
+                stats("0xaa", 2, 300, Some(pnl(1, 100, Some(5)))),
+            ),
+            ("0xbb".to_string(), stats("0xbb", 1, 50, None)),
+        ]);
+        let base = HashMap::from([(
+            "0xaa".to_string(),
+            stats("0xaa", 1, 100, Some(pnl(3, 200, None))),
+        )]);
+
+        let combined = merge_network_stats(vec![("ethereum", ethereum), ("base", base)]);
+        assert_eq!(combined.len(), 2);
+
+        let trader = &combined["0xaa"];
+        assert_eq!(trader.total_buys, 3);
+        assert_eq!(trader.total_volume_usd(), Decimal::from(400));
+        assert_eq!(trader.networks["base"].total_volume_usd, Decimal::from(100));
+        assert_eq!(trader.networks["ethereum"].total_buys, 2);
+
+        let pnl = trader.pnl.as_ref().unwrap();
+        assert_eq!(pnl.realized_pnl_usd, Decimal::from(20));
+        assert_eq!(pnl.position_token, Decimal::from(4));
+        // (1 * 100 + 3 * 200) / 4
+        assert_eq!(pnl.average_entry_price_usd, Some(Decimal::from(175)));
+        assert_eq!(pnl.unrealized_pnl_usd, None);
+
+        assert_eq!(combined["0xbb"].networks.len(), 1);
+    }
+}
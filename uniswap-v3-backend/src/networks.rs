@@ -0,0 +1,208 @@
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::utils::{is_valid_address, load_registry_file, parse_toml};
+
+/// Networks shipped with the binary
+const BUNDLED_NETWORKS: &str = include_str!("../networks/networks.toml");
+
+/// Canonical Uniswap v3 factory, deployed at the same address on most chains
+const DEFAULT_FACTORY_ADDRESS: &str = "0x1F98431c8aD98523631AE4a59f267346ea31F984";
+
+#[derive(Debug, Deserialize)]
+struct NetworkFile {
+    #[serde(default)]
+    networks: Vec<NetworkConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NetworkConfig {
+    /// Canonical lowercase network name used as a key in registries
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    /// Other names accepted for the network, e.g. `mainnet`
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub chain_id: u64,
+    /// Uniswap v3 subgraph on The Graph's gateway
+    pub subgraph_id: Option<String>,
+    /// Full subgraph endpoint, e.g. a self-hosted graph-node; wins over `subgraph_id`
+    pub subgraph_url: Option<String>,
+    /// JSON-RPC endpoint for the rpc source when `RPC_URL` is not set
+    pub rpc_url: Option<String>,
+    #[serde(default = "default_factory_address")]
+    pub factory_address: String,
+    /// Block the Uniswap v3 factory was deployed at; pool discovery starts here
+    #[serde(default)]
+    pub factory_start_block: u64,
+    pub block_time_ms: u64,
+    /// Window used when a request gives no block or time bounds
+    #[serde(default = "default_lookback_days")]
+    pub lookback_days: u64,
+}
+
+fn default_factory_address() -> String {
+    DEFAULT_FACTORY_ADDRESS.to_string()
+}
+
+fn default_lookback_days() -> u64 {
+    30
+}
+
+impl NetworkConfig {
+    /// Blocks in the default lookback window at the network's block time.
+    pub fn default_start_block_offset(&self) -> u64 {
+        self.lookback_days * 86_400 * 1_000 / self.block_time_ms
+    }
+
+    fn validate(&mut self) -> Result<()> {
+        self.id = self.id.trim().to_lowercase();
+        if self.id.is_empty() || self.id == "all" || self.id.contains([',', ':', '/']) {
+            return Err(anyhow!("Invalid network id '{}'", self.id));
+        }
+        if self.name.trim().is_empty() {
+            self.name = self.id.clone();
+        }
+        if self.block_time_ms == 0 {
+            return Err(anyhow!(
+                "Network {} needs a non-zero block_time_ms",
+                self.id
+            ));
+        }
+        let factory = self.factory_address.to_lowercase();
+        if !is_valid_address(&factory) {
+            return Err(anyhow!(
+                "Invalid factory_address '{}' for network {}",
+                self.factory_address,
+                self.id
+            ));
+        }
+        Ok(())
+    }
+
+    fn matches(&self, network: &str) -> bool {
+        self.id.eq_ignore_ascii_case(network)
+            || self
+                .aliases
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(network))
+    }
+}
+
+/// Every network the leaderboard can query, built from the bundled registry
+/// and an optional user file whose entries add networks or replace the
+/// bundled entry with the same id.
+#[derive(Debug, Clone)]
+pub struct NetworkRegistry {
+    networks: Vec<NetworkConfig>,
+}
+
+impl NetworkRegistry {
+    pub fn load(user_file: Option<&str>) -> Result<Self> {
+        let mut registry = Self {
+            networks: Vec::new(),
+        };
+        registry.merge(parse_toml(BUNDLED_NETWORKS, "bundled networks")?)?;
+
+        if let Some(path) = user_file {
+            registry.merge(load_registry_file(Path::new(path), "network file")?)?;
+        }
+
+        Ok(registry)
+    }
+
+    /// Looks a network up by id or alias, ignoring case.
+    pub fn get(&self, network: &str) -> Result<&NetworkConfig> {
+        let network = network.trim();
+        self.networks
+            .iter()
+            .find(|config| config.matches(network))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unsupported network: {}. Supported networks: {}",
+                    network,
+                    self.ids().collect::<Vec<_>>().join(", ")
+                )
+            })
+    }
+
+    /// Canonical ids in registry order.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.networks.iter().map(|config| config.id.as_str())
+    }
+
+    fn merge(&mut self, file: NetworkFile) -> Result<()> {
+        for mut network in file.networks {
+            network.validate()?;
+            match self.networks.iter_mut().find(|n| n.id == network.id) {
+                Some(existing) => *existing = network,
+                None => self.networks.push(network),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_networks_extend_and_override_bundled() {
+        let mut registry = NetworkRegistry::load(None).unwrap();
+        assert_eq!(registry.get("Mainnet").unwrap().id, "ethereum");
+        assert_eq!(
+            registry
+                .get("ethereum")
+                .unwrap()
+                .default_start_block_offset(),
+            216_000
+        );
+        assert_eq!(
+            registry
+                .get("arbitrum")
+                .unwrap()
+                .default_start_block_offset(),
+            10_368_000
+        );
+        assert!(registry.get("celo").is_err());
+
+        let user_file = parse_toml(
+            r#"
+            [[networks]]
+            id = "Celo"
+            chain_id = 42220
+            subgraph_url = "http://localhost:8000/subgraphs/name/uniswap-v3-celo"
+            factory_address = "0xAfE208a311B21f13EF87E33A90049fC17A7acDEc"
+            block_time_ms = 5000
+            lookback_days = 7
+
+            [[networks]]
+            id = "base"
+            chain_id = 8453
+            subgraph_url = "http://localhost:8000/subgraphs/name/uniswap-v3-base"
+            block_time_ms = 2000
+            "#,
+            "test networks",
+        )
+        .unwrap();
+        registry.merge(user_file).unwrap();
+
+        let celo = registry.get("celo").unwrap();
+        assert_eq!((celo.id.as_str(), celo.name.as_str()), ("celo", "celo"));
+        assert_eq!(celo.default_start_block_offset(), 120_960);
+        let base = registry.get("base").unwrap();
+        assert!(base.subgraph_id.is_none());
+        assert_eq!(base.factory_address, DEFAULT_FACTORY_ADDRESS);
+        assert_eq!(registry.ids().count(), 6);
+
+        let invalid = parse_toml(
+            "[[networks]]\nid = \"all\"\nchain_id = 1\nblock_time_ms = 1000",
+            "test",
+        )
+        .unwrap();
+        assert!(registry.merge(invalid).is_err());
+    }
+}
@@ -0,0 +1,430 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use std::io::Write;
+
+use crate::labels::LabelRegistry;
+use crate::pools::{PoolStats, write_pools_table};
+use crate::trader::{TradeSide, TraderHistory, TraderSwap, write_trader_table};
+use crate::types::{
+    LeaderboardResponse, PageInfo, PoolLeaderboardResponse, SummaryStats, SwapWindow,
+    TraderDetailResponse, TraderStats, TraderStatsAPI,
+};
+use crate::utils::{leaderboard_networks, write_leaderboard_table};
+
+/// How CLI results are written. Everything but `table` is meant for other
+/// programs and carries no decoration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+    Markdown,
+    Ndjson,
+}
+
+const LEADERBOARD_COLUMNS: [&str; 17] = [
+    "rank",
+    "address",
+    "label",
+    "category",
+    "total_buys",
+    "total_sells",
+    "total_buy_volume_token",
+    "total_sell_volume_token",
+    "total_buy_volume_usd",
+    "total_sell_volume_usd",
+    "total_volume_usd",
+    "net_volume_token",
+    "buy_sell_ratio",
+    "realized_pnl_usd",
+    "unrealized_pnl_usd",
+    "position_token",
+    "average_entry_price_usd",
+];
+
+const SWAP_COLUMNS: [&str; 8] = [
+    "id",
+    "block_number",
+    "timestamp",
+    "pool",
+    "pair",
+    "side",
+    "amount_token",
+    "amount_usd",
+];
+
+const POOL_COLUMNS: [&str; 15] = [
+    "rank",
+    "pool",
+    "pair",
+    "fee_tier",
+    "swap_count",
+    "total_buys",
+    "total_sells",
+    "buy_volume_usd",
+    "sell_volume_usd",
+    "total_volume_usd",
+    "unique_traders",
+    "last_price",
+    "quote_symbol",
+    "tick",
+    "tvl_usd",
+];
+
+/// Writes the top `limit` of the ranked `traders`. Machine-readable formats
+/// use the same fields as the HTTP API; the JSON summary covers every trader.
+pub fn write_leaderboard(
+    out: &mut dyn Write,
+    format: OutputFormat,
+    traders: &[TraderStats],
+    limit: usize,
+    labels: &LabelRegistry,
+    window: &SwapWindow,
+) -> Result<()> {
+    let rows: Vec<TraderStatsAPI> = traders
+        .iter()
+        .take(limit)
+        .enumerate()
+        .map(|(i, trader)| TraderStatsAPI {
+            rank: Some(i + 1),
+            ..TraderStatsAPI::from_stats(trader.clone(), labels)
+        })
+        .collect();
+    let networks = leaderboard_networks(traders);
+    let mut columns: Vec<String> = LEADERBOARD_COLUMNS.map(String::from).to_vec();
+    for network in &networks {
+        columns.push(format!("{}_trades", network));
+        columns.push(format!("{}_volume_usd", network));
+    }
+    let by_pool = rows.iter().any(|row| !row.pools.is_empty());
+    if by_pool {
+        columns.push("pools".to_string());
+    }
+
+    // Table and JSON report the same totals over every ranked trader
+    let summary = SummaryStats::from_traders(traders);
+    match format {
+        OutputFormat::Table => write_leaderboard_table(out, traders, &summary, limit, labels)?,
+        OutputFormat::Json => {
+            let has_more = rows.len() < traders.len();
+            let response = LeaderboardResponse {
+                traders: &rows,
+                summary: &summary,
+                page: PageInfo {
+                    offset: 0,
+                    limit,
+                    next_offset: has_more.then_some(rows.len()),
+                    next_cursor: None,
+                },
+                window,
+            };
+            serde_json::to_writer_pretty(&mut *out, &response)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Ndjson => write_ndjson(out, &rows)?,
+        OutputFormat::Csv => {
+            write_csv_row(out, &columns)?;
+            for row in &rows {
+                write_csv_row(out, leaderboard_fields(row, &networks, by_pool))?;
+            }
+        }
+        OutputFormat::Markdown => {
+            write_markdown_row(out, &columns)?;
+            write_markdown_row(out, columns.iter().map(|_| "---"))?;
+            for row in &rows {
+                write_markdown_row(out, leaderboard_fields(row, &networks, by_pool))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes a trader's history. Row-based formats list the `limit` swaps after
+/// `offset`; JSON also includes the trader's stats and pool breakdown.
+pub fn write_trader(
+    out: &mut dyn Write,
+    format: OutputFormat,
+    history: &TraderHistory,
+    offset: usize,
+    limit: usize,
+    labels: &LabelRegistry,
+    window: &SwapWindow,
+) -> Result<()> {
+    let page: Vec<&TraderSwap> = history.swaps.iter().skip(offset).take(limit).collect();
+
+    match format {
+        OutputFormat::Table => write_trader_table(out, history, offset, limit, labels)?,
+        OutputFormat::Json => {
+            let response = TraderDetailResponse {
+                trader: TraderStatsAPI::from_stats(history.stats.clone(), labels),
+                pools: history.pools.clone(),
+                swaps: page.into_iter().cloned().collect(),
+                total_swaps: history.swaps.len(),
+                offset,
+                limit,
+                window: window.clone(),
+            };
+            serde_json::to_writer_pretty(&mut *out, &response)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Ndjson => write_ndjson(out, &page)?,
+        OutputFormat::Csv => {
+            write_csv_row(out, SWAP_COLUMNS)?;
+            for swap in page {
+                write_csv_row(out, swap_fields(swap))?;
+            }
+        }
+        OutputFormat::Markdown => {
+            write_markdown_row(out, SWAP_COLUMNS)?;
+            write_markdown_row(out, SWAP_COLUMNS.map(|_| "---"))?;
+            for swap in page {
+                write_markdown_row(out, swap_fields(swap))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the top `limit` of the ranked `pools`; JSON also reports how many
+/// pools traded the token in the window.
+pub fn write_pools(
+    out: &mut dyn Write,
+    format: OutputFormat,
+    pools: &[PoolStats],
+    limit: usize,
+    window: &SwapWindow,
+) -> Result<()> {
+    let rows = &pools[..limit.min(pools.len())];
+
+    match format {
+        OutputFormat::Table => write_pools_table(out, pools, limit)?,
+        OutputFormat::Json => {
+            let response = PoolLeaderboardResponse {
+                pools: rows,
+                total_pools: pools.len(),
+                window,
+            };
+            serde_json::to_writer_pretty(&mut *out, &response)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Ndjson => write_ndjson(out, rows)?,
+        OutputFormat::Csv => {
+            write_csv_row(out, POOL_COLUMNS)?;
+            for pool in rows {
+                write_csv_row(out, pool_fields(pool))?;
+            }
+        }
+        OutputFormat::Markdown => {
+            write_markdown_row(out, POOL_COLUMNS)?;
+            write_markdown_row(out, POOL_COLUMNS.map(|_| "---"))?;
+            for pool in rows {
+                write_markdown_row(out, pool_fields(pool))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// One row's values for `LEADERBOARD_COLUMNS`, then trades and volume on
+/// each of `networks`, then the pool breakdown as
+/// `PAIR FEE (pool): volume` entries separated by `; `.
+fn leaderboard_fields(row: &TraderStatsAPI, networks: &[&str], by_pool: bool) -> Vec<String> {
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    let mut fields = vec![
+        row.rank.map(|rank| rank.to_string()).unwrap_or_default(),
+        row.address.clone(),
+        optional(&row.label),
+        row.category
+            .and_then(|category| category.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default(),
+        row.total_buys.to_string(),
+        row.total_sells.to_string(),
+        row.total_buy_volume_token.clone(),
+        row.total_sell_volume_token.clone(),
+        row.total_buy_volume_usd.clone(),
+        row.total_sell_volume_usd.clone(),
+        row.total_volume_usd.clone(),
+        row.net_volume_token.clone(),
+        row.buy_sell_ratio
+            .map(|ratio| ratio.to_string())
+            .unwrap_or_default(),
+        optional(&row.realized_pnl_usd),
+        optional(&row.unrealized_pnl_usd),
+        optional(&row.position_token),
+        optional(&row.average_entry_price_usd),
+    ];
+    for network in networks {
+        let activity = row.networks.get(*network);
+        fields.push(
+            activity
+                .map(|activity| (activity.total_buys + activity.total_sells).to_string())
+                .unwrap_or_default(),
+        );
+        fields.push(
+            activity
+                .map(|activity| activity.total_volume_usd.to_string())
+                .unwrap_or_default(),
+        );
+    }
+    if by_pool {
+        let pools: Vec<String> = row
+            .pools
+            .iter()
+            .map(|pool| {
+                format!(
+                    "{} ({}): {}",
+                    pool.label(),
+                    pool.pool,
+                    pool.total_volume_usd
+                )
+            })
+            .collect();
+        fields.push(pools.join("; "));
+    }
+    fields
+}
+
+fn swap_fields(swap: &TraderSwap) -> [String; 8] {
+    [
+        swap.id.clone(),
+        swap.block_number.to_string(),
+        swap.timestamp.to_string(),
+        swap.pool.clone(),
+        swap.pair.clone(),
+        match swap.side {
+            TradeSide::Buy => "buy".to_string(),
+            TradeSide::Sell => "sell".to_string(),
+        },
+        swap.amount_token.to_string(),
+        swap.amount_usd.to_string(),
+    ]
+}
+
+fn pool_fields(pool: &PoolStats) -> [String; 15] {
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    [
+        optional(pool.rank.map(|rank| rank.to_string())),
+        pool.pool.clone(),
+        pool.pair.clone(),
+        optional(pool.fee_tier.map(|fee| fee.to_string())),
+        pool.swap_count.to_string(),
+        pool.total_buys.to_string(),
+        pool.total_sells.to_string(),
+        pool.buy_volume_usd.to_string(),
+        pool.sell_volume_usd.to_string(),
+        pool.total_volume_usd.to_string(),
+        pool.unique_traders.to_string(),
+        optional(pool.last_price.map(|price| price.to_string())),
+        pool.quote_symbol.clone(),
+        optional(pool.tick.map(|tick| tick.to_string())),
+        optional(pool.tvl_usd.map(|tvl| tvl.to_string())),
+    ]
+}
+
+fn write_ndjson<T: serde::Serialize>(out: &mut dyn Write, rows: &[T]) -> Result<()> {
+    for row in rows {
+        serde_json::to_writer(&mut *out, row)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn write_csv_row<S: AsRef<str>>(
+    out: &mut dyn Write,
+    fields: impl IntoIterator<Item = S>,
+) -> Result<()> {
+    let fields: Vec<String> = fields
+        .into_iter()
+        .map(|field| csv_field(field.as_ref()))
+        .collect();
+    writeln!(out, "{}", fields.join(","))?;
+    Ok(())
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn write_markdown_row<S: AsRef<str>>(
+    out: &mut dyn Write,
+    cells: impl IntoIterator<Item = S>,
+) -> Result<()> {
+    let cells: Vec<String> = cells
+        .into_iter()
+        .map(|cell| cell.as_ref().replace('|', "\\|"))
+        .collect();
+    writeln!(out, "| {} |", cells.join(" | "))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn trader(address: &str, buy_volume: i64) -> TraderStats {
+        TraderStats {
+            total_buys: 1,
+            total_buy_volume_usd: Decimal::from(buy_volume),
+            ..TraderStats::new(address.to_string())
+        }
+    }
+
+    #[test]
+    fn test_machine_formats_write_one_row_per_trader() {
+        let traders = [
+            trader("0xaa", 300),
+            trader("0xbb", 200),
+            trader("0xcc", 100),
+        ];
+        let labels = LabelRegistry::default();
+        let render = |format| {
+            let mut out = Vec::new();
+            write_leaderboard(
+                &mut out,
+                format,
+                &traders,
+                2,
+                &labels,
+                &SwapWindow::default(),
+            )
+            .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let csv = render(OutputFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("rank,address,label,category,"));
+        assert!(lines[1].starts_with("1,0xaa,,,1,0,"));
+
+        let ndjson = render(OutputFormat::Ndjson);
+        let rows: Vec<serde_json::Value> = ndjson
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows[1]["address"], "0xbb");
+        assert_eq!(rows[1]["rank"], 2);
+
+        let json: serde_json::Value = serde_json::from_str(&render(OutputFormat::Json)).unwrap();
+        assert_eq!(json["traders"].as_array().unwrap().len(), 2);
+        assert_eq!(json["summary"]["total_traders"], 3);
+        assert_eq!(json["summary"]["total_volume_usd"], "600.00");
+
+        // The table reports the same summary as the JSON
+        let table = render(OutputFormat::Table);
+        assert!(table.contains("Total Traders: 3\n"));
+        assert!(table.contains("Total Volume (USD): $600.00\n"));
+        assert!(table.contains("Average Volume per Trader: $200.00\n"));
+
+        assert_eq!(render(OutputFormat::Markdown).lines().count(), 4);
+        assert_eq!(csv_field("Wintermute, Inc"), "\"Wintermute, Inc\"");
+    }
+}
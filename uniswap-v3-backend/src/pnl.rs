@@ -0,0 +1,192 @@
+use clap::ValueEnum;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// How sells are matched against earlier buys when realizing PnL.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CostBasisMode {
+    /// Sells consume the oldest open buys first
+    #[default]
+    Fifo,
+    /// Sells are charged the running average cost of the position
+    Average,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraderPnl {
+    pub realized_pnl_usd: Decimal,
+    /// Tokens still held from buys inside the window
+    pub position_token: Decimal,
+    pub average_entry_price_usd: Option<Decimal>,
+    /// `None` when no mark price could be derived for the token
+    pub unrealized_pnl_usd: Option<Decimal>,
+}
+
+impl TraderPnl {
+    pub fn total_pnl_usd(&self) -> Decimal {
+        self.realized_pnl_usd + self.unrealized_pnl_usd.unwrap_or(Decimal::ZERO)
+    }
+
+    /// Adds a separately tracked position in the same token, e.g. on another
+    /// network. Unrealized PnL stays unknown unless both sides were marked.
+    pub fn merge(&mut self, other: &TraderPnl) {
+        let open_cost = |pnl: &TraderPnl| {
+            pnl.average_entry_price_usd.unwrap_or(Decimal::ZERO) * pnl.position_token
+        };
+        let cost = open_cost(self) + open_cost(other);
+
+        self.realized_pnl_usd += other.realized_pnl_usd;
+        self.position_token += other.position_token;
+        self.average_entry_price_usd =
+            (self.position_token > Decimal::ZERO).then(|| cost / self.position_token);
+        self.unrealized_pnl_usd = self
+            .unrealized_pnl_usd
+            .zip(other.unrealized_pnl_usd)
+            .map(|(a, b)| a + b);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Lot {
+    quantity: Decimal,
+    unit_cost_usd: Decimal,
+}
+
+/// Tracks one trader's position in the target token across an ordered stream
+/// of buys and sells.
+///
+/// Only buys seen inside the window have a known cost, so the part of a sell
+/// that exceeds the open position (tokens acquired before the window) is left
+/// out of realized PnL rather than guessed at.
+#[derive(Debug, Clone)]
+pub struct PositionTracker {
+    mode: CostBasisMode,
+    lots: VecDeque<Lot>,
+    quantity: Decimal,
+    cost_usd: Decimal,
+    realized_pnl_usd: Decimal,
+}
+
+impl PositionTracker {
+    pub fn new(mode: CostBasisMode) -> Self {
+        Self {
+            mode,
+            lots: VecDeque::new(),
+            quantity: Decimal::ZERO,
+            cost_usd: Decimal::ZERO,
+            realized_pnl_usd: Decimal::ZERO,
+        }
+    }
+
+    pub fn buy(&mut self, quantity: Decimal, usd: Decimal) {
+        if quantity <= Decimal::ZERO {
+            return;
+        }
+        self.quantity += quantity;
+        self.cost_usd += usd;
+        if self.mode == CostBasisMode::Fifo {
+            self.lots.push_back(Lot {
+                quantity,
+                unit_cost_usd: usd / quantity,
+            });
+        }
+    }
+
+    pub fn sell(&mut self, quantity: Decimal, usd: Decimal) {
+        if quantity <= Decimal::ZERO || self.quantity <= Decimal::ZERO {
+            return;
+        }
+        let price = usd / quantity;
+        let matched = quantity.min(self.quantity);
+
+        let matched_cost = match self.mode {
+            CostBasisMode::Fifo => {
+                let mut remaining = matched;
+                let mut cost = Decimal::ZERO;
+                while remaining > Decimal::ZERO {
+                    let Some(lot) = self.lots.front_mut() else {
+                        break;
+                    };
+                    let take = remaining.min(lot.quantity);
+                    cost += take * lot.unit_cost_usd;
+                    lot.quantity -= take;
+                    remaining -= take;
+                    if lot.quantity <= Decimal::ZERO {
+                        self.lots.pop_front();
+                    }
+                }
+                cost
+            }
+            CostBasisMode::Average => matched * self.cost_usd / self.quantity,
+        };
+
+        self.realized_pnl_usd += matched * price - matched_cost;
+        self.quantity -= matched;
+        self.cost_usd -= matched_cost;
+        if self.quantity <= Decimal::ZERO {
+            self.quantity = Decimal::ZERO;
+            self.cost_usd = Decimal::ZERO;
+            self.lots.clear();
+        }
+    }
+
+    /// Snapshot of the position, marking any open tokens at `mark_price_usd`.
+    pub fn pnl(&self, mark_price_usd: Option<Decimal>) -> TraderPnl {
+        let average_entry_price_usd =
+            (self.quantity > Decimal::ZERO).then(|| self.cost_usd / self.quantity);
+
+        TraderPnl {
+            realized_pnl_usd: self.realized_pnl_usd,
+            position_token: self.quantity,
+            average_entry_price_usd,
+            unrealized_pnl_usd: mark_price_usd.map(|mark| self.quantity * mark - self.cost_usd),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: i64) -> Decimal {
+        Decimal::from(value)
+    }
+
+    #[test]
+    fn test_fifo_and_average_cost_realize_differently() {
+        let mut fifo = PositionTracker::new(CostBasisMode::Fifo);
+        let mut average = PositionTracker::new(CostBasisMode::Average);
+        for tracker in [&mut fifo, &mut average] {
+            tracker.buy(dec(1), dec(100));
+            tracker.buy(dec(1), dec(200));
+            tracker.sell(dec(1), dec(250));
+        }
+
+        let fifo = fifo.pnl(Some(dec(300)));
+        assert_eq!(fifo.realized_pnl_usd, dec(150));
+        assert_eq!(fifo.position_token, dec(1));
+        assert_eq!(fifo.average_entry_price_usd, Some(dec(200)));
+        assert_eq!(fifo.unrealized_pnl_usd, Some(dec(100)));
+
+        let average = average.pnl(Some(dec(300)));
+        assert_eq!(average.realized_pnl_usd, dec(100));
+        assert_eq!(average.average_entry_price_usd, Some(dec(150)));
+        assert_eq!(average.unrealized_pnl_usd, Some(dec(150)));
+    }
+
+    #[test]
+    fn test_sells_beyond_position_are_not_realized() {
+        let mut tracker = PositionTracker::new(CostBasisMode::Fifo);
+        tracker.sell(dec(5), dec(500));
+        tracker.buy(dec(2), dec(200));
+        tracker.sell(dec(3), dec(360));
+
+        let pnl = tracker.pnl(None);
+        assert_eq!(pnl.realized_pnl_usd, dec(40));
+        assert_eq!(pnl.position_token, Decimal::ZERO);
+        assert_eq!(pnl.average_entry_price_usd, None);
+        assert_eq!(pnl.unrealized_pnl_usd, None);
+    }
+}
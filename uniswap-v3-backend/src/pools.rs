@@ -0,0 +1,282 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+use crate::types::{AggregationOptions, PoolSortKey, SortOrder, Swap};
+use crate::utils::{
+    chronological_key, determine_trade_type, pair_label, parse_decimal, pool_label, pool_price,
+};
+
+/// Activity in one pool trading the target token.
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolStats {
+    /// Position after `rank_pools`
+    pub rank: Option<usize>,
+    pub pool: String,
+    /// Pool tokens as `TOKEN0/TOKEN1`
+    pub pair: String,
+    /// Hundredths of a basis point; `None` when the source did not report it
+    pub fee_tier: Option<u32>,
+    pub swap_count: u32,
+    pub total_buys: u32,
+    pub total_sells: u32,
+    pub buy_volume_usd: Decimal,
+    pub sell_volume_usd: Decimal,
+    pub total_volume_usd: Decimal,
+    /// Distinct addresses credited with a swap, per the attribution options
+    pub unique_traders: usize,
+    /// Target token priced in `quote_symbol` from the pool state reported
+    /// with its latest swap: the pool's current price for the subgraph, the
+    /// price emitted by that swap for JSON-RPC
+    pub last_price: Option<f64>,
+    /// Symbol of the pool's other token
+    pub quote_symbol: String,
+    /// Pool tick, from the same pool state as `last_price`
+    pub tick: Option<i32>,
+    /// Pool's current total value locked, when the swap source reports it
+    pub tvl_usd: Option<Decimal>,
+}
+
+impl PoolStats {
+    /// Pair and fee tier, e.g. `USDC/WETH 0.05%`.
+    pub fn label(&self) -> String {
+        pool_label(&self.pair, self.fee_tier)
+    }
+}
+
+/// Aggregates `swaps` of `target_token` by pool. Price, tick and TVL are read
+/// from the pool state attached to each pool's latest swap. The subgraph
+/// attaches the pool entity as of the query, so there they are the pool's
+/// current values rather than its state at that swap. Traders are counted with
+/// the same attribution as the trader leaderboard.
+pub fn aggregate_pool_stats(
+    swaps: &[Swap],
+    target_token: &str,
+    options: &AggregationOptions,
+) -> Result<Vec<PoolStats>> {
+    let target_token = target_token.to_lowercase();
+    let mut pools: HashMap<String, (PoolStats, HashSet<String>)> = HashMap::new();
+
+    let mut ordered: Vec<&Swap> = swaps.iter().collect();
+    ordered.sort_by_cached_key(|swap| chronological_key(swap));
+
+    for swap in ordered {
+        let (is_buy, _, amount_usd) = match determine_trade_type(swap, &target_token) {
+            Ok(trade) => trade,
+            Err(e) => {
+                eprintln!("Warning: Failed to process swap {}: {}", swap.id, e);
+                continue;
+            }
+        };
+        let pool = &swap.pool;
+        let target_is_token0 = pool.token_0.id.eq_ignore_ascii_case(&target_token);
+
+        let (stats, traders) = pools.entry(pool.id.to_lowercase()).or_insert_with(|| {
+            let stats = PoolStats {
+                rank: None,
+                pool: pool.id.to_lowercase(),
+                pair: pair_label(pool),
+                fee_tier: pool.fee(),
+                swap_count: 0,
+                total_buys: 0,
+                total_sells: 0,
+                buy_volume_usd: Decimal::ZERO,
+                sell_volume_usd: Decimal::ZERO,
+                total_volume_usd: Decimal::ZERO,
+                unique_traders: 0,
+                last_price: None,
+                quote_symbol: if target_is_token0 {
+                    pool.token_1.symbol.clone()
+                } else {
+                    pool.token_0.symbol.clone()
+                },
+                tick: None,
+                tvl_usd: None,
+            };
+            (stats, HashSet::new())
+        });
+
+        stats.swap_count += 1;
+        if is_buy {
+            stats.total_buys += 1;
+            stats.buy_volume_usd += amount_usd;
+        } else {
+            stats.total_sells += 1;
+            stats.sell_volume_usd += amount_usd;
+        }
+        stats.total_volume_usd += amount_usd;
+        if let Some(trader) = options.trader_address(swap) {
+            traders.insert(trader.to_lowercase());
+        }
+
+        // pool_price is token0 in token1
+        if let Some(price) = pool_price(pool) {
+            stats.last_price = Some(if target_is_token0 { price } else { 1.0 / price });
+        }
+        if let Some(tick) = pool.tick.as_deref().and_then(|tick| tick.parse().ok()) {
+            stats.tick = Some(tick);
+        }
+        if let Some(tvl) = pool
+            .total_value_locked_usd
+            .as_deref()
+            .and_then(|tvl| parse_decimal(tvl).ok())
+        {
+            stats.tvl_usd = Some(tvl);
+        }
+    }
+
+    Ok(pools
+        .into_values()
+        .map(|(stats, traders)| PoolStats {
+            unique_traders: traders.len(),
+            ..stats
+        })
+        .collect())
+}
+
+/// Orders pools by `sort_by` in the given direction and numbers them from 1.
+/// Ties are broken by ascending pool address, and when ranking by TVL, pools
+/// without a TVL always come last.
+pub fn rank_pools(
+    mut pools: Vec<PoolStats>,
+    sort_by: PoolSortKey,
+    order: SortOrder,
+) -> Vec<PoolStats> {
+    let missing_metric = |p: &PoolStats| sort_by == PoolSortKey::Tvl && p.tvl_usd.is_none();
+
+    pools.sort_by(|a, b| {
+        missing_metric(a)
+            .cmp(&missing_metric(b))
+            .then_with(|| {
+                let ordering = match sort_by {
+                    PoolSortKey::Volume => a.total_volume_usd.cmp(&b.total_volume_usd),
+                    PoolSortKey::Swaps => a.swap_count.cmp(&b.swap_count),
+                    PoolSortKey::Traders => a.unique_traders.cmp(&b.unique_traders),
+                    PoolSortKey::Tvl => a.tvl_usd.cmp(&b.tvl_usd),
+                };
+                match order {
+                    SortOrder::Asc => ordering,
+                    SortOrder::Desc => ordering.reverse(),
+                }
+            })
+            .then_with(|| a.pool.cmp(&b.pool))
+    });
+    for (i, pool) in pools.iter_mut().enumerate() {
+        pool.rank = Some(i + 1);
+    }
+    pools
+}
+
+/// Writes the top `limit` of `pools`, which must already be ranked (see
+/// `rank_pools`), as a fixed-width table.
+pub fn write_pools_table(out: &mut dyn Write, pools: &[PoolStats], limit: usize) -> io::Result<()> {
+    writeln!(out, "\n🏊 UNISWAP V3 POOL LEADERBOARD 🏊")?;
+    writeln!(
+        out,
+        "═══════════════════════════════════════════════════════════════════════════════════════"
+    )?;
+    writeln!(
+        out,
+        "{:<4} {:<42} {:<20} {:<8} {:<8} {:<8} {:<15} {:<8} {:<20} {:<15}",
+        "Rank",
+        "Pool",
+        "Pair",
+        "Swaps",
+        "Buys",
+        "Sells",
+        "Volume USD",
+        "Traders",
+        "Last Price",
+        "TVL USD"
+    )?;
+    writeln!(
+        out,
+        "─────────────────────────────────────────────────────────────────────────────────────────"
+    )?;
+
+    for (i, pool) in pools.iter().take(limit).enumerate() {
+        let last_price = match pool.last_price {
+            Some(price) if price >= 1.0 => format!("{:.2} {}", price, pool.quote_symbol),
+            Some(price) => format!("{:.6} {}", price, pool.quote_symbol),
+            None => "n/a".to_string(),
+        };
+        let tvl = pool
+            .tvl_usd
+            .map(|tvl| format!("${:.2}", tvl))
+            .unwrap_or_else(|| "n/a".to_string());
+
+        writeln!(
+            out,
+            "{:<4} {:<42} {:<20} {:<8} {:<8} {:<8} ${:<14.2} {:<8} {:<20} {:<15}",
+            pool.rank.unwrap_or(i + 1),
+            pool.pool,
+            pool.label(),
+            pool.swap_count,
+            pool.total_buys,
+            pool.total_sells,
+            pool.total_volume_usd,
+            pool.unique_traders,
+            last_price,
+            tvl
+        )?;
+    }
+
+    writeln!(
+        out,
+        "═══════════════════════════════════════════════════════════════════════════════════════"
+    )?;
+
+    let total_swaps: u32 = pools.iter().map(|p| p.swap_count).sum();
+    let total_volume: Decimal = pools.iter().map(|p| p.total_volume_usd).sum();
+
+    writeln!(out, "\n📊 SUMMARY STATISTICS")?;
+    writeln!(out, "─────────────────────")?;
+    writeln!(out, "Total Pools: {}", pools.len())?;
+    writeln!(out, "Total Swaps: {}", total_swaps)?;
+    writeln!(out, "Total Volume (USD): ${:.2}", total_volume)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+
+    #[test]
+    fn test_pools_are_aggregated_and_ranked() {
+        let swaps: Vec<Swap> =
+            serde_json::from_str(include_str!("../fixtures/swaps.json")).unwrap();
+        let pools = aggregate_pool_stats(&swaps, WETH, &AggregationOptions::default()).unwrap();
+
+        let pools = rank_pools(pools, PoolSortKey::Volume, SortOrder::Desc);
+        assert_eq!(pools.len(), 2);
+        let usdc = &pools[0];
+        assert_eq!(usdc.label(), "USDC/WETH 0.05%");
+        assert_eq!(usdc.rank, Some(1));
+        assert_eq!(
+            (usdc.swap_count, usdc.total_buys, usdc.total_sells),
+            (4, 2, 2)
+        );
+        assert_eq!(usdc.total_volume_usd, Decimal::from(17_900));
+        assert_eq!(usdc.unique_traders, 2);
+        assert_eq!(usdc.quote_symbol, "USDC");
+        assert_eq!(usdc.tick, Some(197_496));
+        assert_eq!(usdc.tvl_usd, Some(Decimal::new(2_150_000_005, 1)));
+        // WETH is token1, so the pool's USDC-in-WETH price is inverted
+        let price = usdc.last_price.unwrap();
+        assert!((2_600.0..2_700.0).contains(&price), "{}", price);
+
+        // Equal trader counts fall back to the pool address
+        let by_traders = rank_pools(pools, PoolSortKey::Traders, SortOrder::Desc);
+        assert_eq!(by_traders[0].pair, "WETH/USDT");
+        assert_eq!(by_traders[0].unique_traders, 2);
+
+        let mut pools = by_traders;
+        pools[0].tvl_usd = None;
+        let by_tvl = rank_pools(pools, PoolSortKey::Tvl, SortOrder::Asc);
+        assert_eq!(by_tvl[1].pair, "WETH/USDT");
+    }
+}
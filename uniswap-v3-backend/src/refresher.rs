@@ -0,0 +1,398 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+use tokio::sync::{Notify, broadcast};
+
+use crate::config::Config;
+use crate::live::{self, LiveUpdate};
+use crate::networks::NetworkRegistry;
+use crate::sources::build_swap_source;
+use crate::store::SharedSwapStore;
+use crate::types::{AggregationOptions, PoolFilter, Swap, SwapWindow, TraderStats};
+use crate::utils::{
+    aggregate_trader_stats, chronological_key, default_start_block, is_valid_address,
+};
+
+/// A token whose default leaderboard the server keeps warm.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct WatchedToken {
+    pub network: String,
+    pub token_address: String,
+}
+
+impl WatchedToken {
+    /// Validates both parts and normalizes them to the canonical network id
+    /// and a lowercase address.
+    pub fn new(networks: &NetworkRegistry, network: &str, token_address: &str) -> Result<Self> {
+        let network = networks.get(network)?.id.clone();
+        let token_address = token_address.trim().to_lowercase();
+        if !is_valid_address(&token_address) {
+            return Err(anyhow!("Invalid watched token address: {}", token_address));
+        }
+        Ok(Self {
+            network,
+            token_address,
+        })
+    }
+
+    /// Parses `network:0x…`, or a bare `0x…` address on ethereum.
+    pub fn parse(s: &str, networks: &NetworkRegistry) -> Result<Self> {
+        match s.trim().split_once(':') {
+            Some((network, token)) => Self::new(networks, network, token),
+            None => Self::new(networks, "ethereum", s),
+        }
+    }
+}
+
+/// Swaps for a watched token over its network's default lookback window.
+#[derive(Debug)]
+pub struct WarmSwaps {
+    pub swaps: Vec<Swap>,
+    /// Bounds of `swaps`; `end_block` is the last block synced so far
+    pub window: SwapWindow,
+    /// `swaps` aggregated with the default `AggregationOptions`
+    pub trader_stats: HashMap<String, TraderStats>,
+    /// Unix time of the refresh that produced these swaps
+    pub refreshed_at: u64,
+}
+
+impl WarmSwaps {
+    /// Aggregates `swaps` of `token_address` once, so default leaderboards
+    /// and live updates read the stats instead of recomputing them.
+    pub fn new(
+        token_address: &str,
+        swaps: Vec<Swap>,
+        window: SwapWindow,
+        refreshed_at: u64,
+    ) -> Result<Self> {
+        let trader_stats =
+            aggregate_trader_stats(&swaps, token_address, &AggregationOptions::default())?;
+        Ok(Self {
+            swaps,
+            window,
+            trader_stats,
+            refreshed_at,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct WatchStatus {
+    #[serde(flatten)]
+    pub token: WatchedToken,
+    pub swaps: Option<usize>,
+    pub refreshed_at: Option<u64>,
+}
+
+type Entries = BTreeMap<WatchedToken, Option<Arc<WarmSwaps>>>;
+
+/// Updates buffered per live channel before slow subscribers start skipping
+const LIVE_CHANNEL_CAPACITY: usize = 64;
+
+/// Watched tokens and their most recently refreshed swaps.
+#[derive(Debug, Default)]
+pub struct Watchlist {
+    entries: RwLock<Entries>,
+    /// Wakes the refresher when a token is added
+    added: Notify,
+    /// Live update channels for tokens that have had subscribers
+    live: Mutex<HashMap<WatchedToken, broadcast::Sender<Arc<LiveUpdate>>>>,
+}
+
+impl Watchlist {
+    pub fn new(tokens: impl IntoIterator<Item = WatchedToken>) -> Self {
+        let watchlist = Self::default();
+        for token in tokens {
+            watchlist.add(token);
+        }
+        watchlist
+    }
+
+    /// Returns false if the token was already watched.
+    pub fn add(&self, token: WatchedToken) -> bool {
+        let added = self.write().insert(token, None).is_none();
+        if added {
+            self.added.notify_one();
+        }
+        added
+    }
+
+    /// Returns false if the token wasn't watched. Live subscribers of the
+    /// token see their stream end.
+    pub fn remove(&self, token: &WatchedToken) -> bool {
+        self.live_channels().remove(token);
+        self.write().remove(token).is_some()
+    }
+
+    /// Subscribes to updates published after each refresh of a watched token.
+    pub fn subscribe(&self, token: &WatchedToken) -> Option<broadcast::Receiver<Arc<LiveUpdate>>> {
+        if !self.read().contains_key(token) {
+            return None;
+        }
+        let mut channels = self.live_channels();
+        let sender = channels
+            .entry(token.clone())
+            .or_insert_with(|| broadcast::channel(LIVE_CHANNEL_CAPACITY).0);
+        Some(sender.subscribe())
+    }
+
+    pub fn tokens(&self) -> Vec<WatchedToken> {
+        self.read().keys().cloned().collect()
+    }
+
+    pub fn warm(&self, token: &WatchedToken) -> Option<Arc<WarmSwaps>> {
+        self.read().get(token).cloned().flatten()
+    }
+
+    pub fn status(&self) -> Vec<WatchStatus> {
+        self.read()
+            .iter()
+            .map(|(token, warm)| WatchStatus {
+                token: token.clone(),
+                swaps: warm.as_ref().map(|warm| warm.swaps.len()),
+                refreshed_at: warm.as_ref().map(|warm| warm.refreshed_at),
+            })
+            .collect()
+    }
+
+    /// Stores refreshed swaps, unless the token was unwatched meanwhile, and
+    /// publishes what changed to any live subscribers.
+    fn set_warm(&self, token: &WatchedToken, warm: WarmSwaps) {
+        let warm = Arc::new(warm);
+        let previous = match self.write().get_mut(token) {
+            Some(entry) => entry.replace(warm.clone()),
+            None => return,
+        };
+
+        let Some(sender) = self
+            .live_channels()
+            .get(token)
+            .filter(|sender| sender.receiver_count() > 0)
+            .cloned()
+        else {
+            return;
+        };
+        if let Some(update) = previous.and_then(|previous| live::diff(token, &previous, &warm)) {
+            // Sending only fails when every subscriber has gone away
+            let _ = sender.send(Arc::new(update));
+        }
+    }
+
+    fn live_channels(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<WatchedToken, broadcast::Sender<Arc<LiveUpdate>>>> {
+        self.live.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Entries> {
+        self.entries.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Entries> {
+        self.entries.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Refreshes every watched token each `interval`, and newly added tokens as
+/// soon as they are added.
+pub fn spawn_refresher(
+    watchlist: Arc<Watchlist>,
+    config: Config,
+    store: Option<SharedSwapStore>,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        let mut cold_only = false;
+        loop {
+            for token in watchlist.tokens() {
+                let previous = watchlist.warm(&token);
+                if cold_only && previous.is_some() {
+                    continue;
+                }
+                match refresh(&token, &config, store.as_ref(), previous.as_deref()).await {
+                    Ok(warm) => {
+                        println!(
+                            "♻️  Refreshed {} on {}: {} swaps",
+                            token.token_address,
+                            token.network,
+                            warm.swaps.len()
+                        );
+                        watchlist.set_warm(&token, warm);
+                    }
+                    Err(e) => eprintln!(
+                        "Failed to refresh {} on {}: {}",
+                        token.token_address, token.network, e
+                    ),
+                }
+            }
+
+            cold_only = tokio::select! {
+                _ = tokio::time::sleep(interval) => false,
+                _ = watchlist.added.notified() => true,
+            };
+        }
+    });
+}
+
+/// Brings `token`'s warm swaps up to the latest block. Only blocks after the
+/// previous refresh are fetched; swaps that slid out of the lookback window
+/// are dropped.
+async fn refresh(
+    token: &WatchedToken,
+    config: &Config,
+    store: Option<&SharedSwapStore>,
+    previous: Option<&WarmSwaps>,
+) -> Result<WarmSwaps> {
+    let network_config = config.networks.get(&token.network)?;
+    let source = build_swap_source(config.swap_source, &token.network, config, store)?;
+    let latest_block = source.latest_block().await?;
+    let start_block =
+        default_start_block(latest_block, network_config.default_start_block_offset());
+
+    let synced = previous.and_then(|previous| {
+        previous
+            .window
+            .end_block
+            .filter(|end| *end >= start_block && *end <= latest_block)
+            .map(|end| (&previous.swaps, end))
+    });
+    let fetch_from = synced.map_or(start_block, |(_, end)| end + 1);
+    let fetched = if fetch_from > latest_block {
+        Vec::new()
+    } else {
+        let new_blocks = SwapWindow {
+            start_block: Some(fetch_from),
+            end_block: Some(latest_block),
+            ..Default::default()
+        };
+        source
+            .fetch_swaps(&token.token_address, &new_blocks, &PoolFilter::default())
+            .await?
+    };
+
+    // A capped fetch holds only the newest swaps, so appending it would leave
+    // a gap; it then stands in for the whole window as a full fetch would
+    let capped = config.target_swaps.is_some_and(|cap| fetched.len() >= cap);
+    let swaps = match synced {
+        Some((warm, _)) if !capped => append_swaps(warm, fetched, start_block),
+        _ => fetched,
+    };
+
+    let window = SwapWindow {
+        start_block: Some(start_block),
+        end_block: Some(latest_block),
+        ..Default::default()
+    };
+    WarmSwaps::new(
+        &token.token_address,
+        swaps,
+        window,
+        chrono::Utc::now().timestamp() as u64,
+    )
+}
+
+/// Appends newly fetched swaps to the warm ones, dropping swaps before
+/// `start_block` and any that were already held.
+fn append_swaps(warm: &[Swap], fetched: Vec<Swap>, start_block: u64) -> Vec<Swap> {
+    let mut seen = HashSet::new();
+    warm.iter()
+        .cloned()
+        .chain(fetched)
+        .filter(|swap| chronological_key(swap).0 >= start_block)
+        .filter(|swap| seen.insert(swap.id.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WETH: &str = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+
+    fn watched(s: &str) -> Result<WatchedToken> {
+        WatchedToken::parse(s, &NetworkRegistry::load(None).unwrap())
+    }
+
+    #[test]
+    fn test_watched_token_parsing_normalizes() {
+        let bare = watched(WETH).unwrap();
+        assert_eq!(bare.network, "ethereum");
+        assert_eq!(bare.token_address, WETH.to_lowercase());
+
+        let scoped = watched(&format!("Base:{}", WETH)).unwrap();
+        assert_eq!(scoped.network, "base");
+        assert!(watched("moon:0x00").is_err());
+    }
+
+    #[test]
+    fn test_refreshed_swaps_are_dropped_for_unwatched_tokens() {
+        let token = watched(WETH).unwrap();
+        let watchlist = Watchlist::new([token.clone()]);
+        assert!(!watchlist.add(token.clone()));
+
+        let warm = || WarmSwaps::new(WETH, Vec::new(), SwapWindow::default(), 1).unwrap();
+        watchlist.set_warm(&token, warm());
+        assert_eq!(watchlist.status()[0].refreshed_at, Some(1));
+
+        assert!(watchlist.remove(&token));
+        watchlist.set_warm(&token, warm());
+        assert!(watchlist.warm(&token).is_none());
+        assert!(watchlist.status().is_empty());
+    }
+
+    #[test]
+    fn test_subscribers_receive_changes_between_refreshes() {
+        let swaps: Vec<Swap> =
+            serde_json::from_str(include_str!("../fixtures/swaps.json")).unwrap();
+        let token = watched(WETH).unwrap();
+        let watchlist = Watchlist::new([token.clone()]);
+        assert!(
+            watchlist
+                .subscribe(&watched("base:0x4200000000000000000000000000000000000006").unwrap())
+                .is_none()
+        );
+
+        let mut receiver = watchlist.subscribe(&token).unwrap();
+        let warm = |swaps: Vec<Swap>, refreshed_at| {
+            WarmSwaps::new(WETH, swaps, SwapWindow::default(), refreshed_at).unwrap()
+        };
+        watchlist.set_warm(&token, warm(swaps[..2].to_vec(), 1));
+        watchlist.set_warm(&token, warm(swaps.clone(), 2));
+        watchlist.set_warm(&token, warm(swaps, 3));
+
+        // The first refresh has nothing to diff against and the last changed nothing
+        let update = receiver.try_recv().unwrap();
+        assert_eq!(update.refreshed_at, 2);
+        assert!(!update.new_swaps.is_empty());
+        assert!(receiver.try_recv().is_err());
+
+        watchlist.remove(&token);
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(broadcast::error::TryRecvError::Closed)
+        ));
+    }
+
+    #[test]
+    fn test_refreshes_append_new_blocks_to_the_warm_swaps() {
+        let swaps: Vec<Swap> =
+            serde_json::from_str(include_str!("../fixtures/swaps.json")).unwrap();
+        let block = |swap: &Swap| chronological_key(swap).0;
+        let first = swaps.iter().map(block).min().unwrap();
+        let last = swaps.iter().map(block).max().unwrap();
+        assert!(first < last);
+
+        // The refetched tail overlaps what is already warm
+        let warm: Vec<Swap> = swaps.iter().filter(|s| block(s) < last).cloned().collect();
+        let fetched: Vec<Swap> = swaps.iter().filter(|s| block(s) > first).cloned().collect();
+        let appended = append_swaps(&warm, fetched, first);
+        assert_eq!(appended.len(), swaps.len());
+
+        // The window slid past the first block
+        let slid = append_swaps(&appended, Vec::new(), first + 1);
+        assert!(slid.iter().all(|swap| block(swap) > first));
+        assert!(slid.len() < swaps.len());
+    }
+}
@@ -3,22 +3,99 @@ use axum::{
     Router,
     routing::{get, post},
 };
+use std::sync::Arc;
 use tower_http::cors::CorsLayer;
 
-use crate::handlers::{health_check, leaderboard_handler};
+use crate::cache::ResponseCache;
+use crate::config::Config;
+use crate::handlers::{
+    RankedLeaderboard, add_to_watchlist, health_check, leaderboard_handler, leaderboard_stream,
+    list_watchlist, pools_handler, remove_from_watchlist, token_leaderboard_handler,
+    trader_handler,
+};
+use crate::refresher::{Watchlist, spawn_refresher};
+use crate::sources::open_swap_store;
+use crate::store::SharedSwapStore;
+
+/// Shared state handed to every request handler.
+#[derive(Clone)]
+pub struct AppState {
+    pub leaderboard_cache: Arc<ResponseCache<RankedLeaderboard>>,
+    pub watchlist: Arc<Watchlist>,
+    /// Settings validated at startup, shared by every request
+    pub config: Arc<Config>,
+    /// Opened once at startup when `STORE_PATH` is set
+    pub swap_store: Option<SharedSwapStore>,
+}
 
-pub async fn run_server(port: u16) -> Result<()> {
+pub async fn run_server(port: u16, config: &Config) -> Result<()> {
+    let state = AppState {
+        leaderboard_cache: Arc::new(ResponseCache::new(config.cache_ttl)),
+        watchlist: Arc::new(Watchlist::new(config.watchlist.clone())),
+        config: Arc::new(config.clone()),
+        swap_store: open_swap_store(config)?,
+    };
+    spawn_refresher(
+        state.watchlist.clone(),
+        config.clone(),
+        state.swap_store.clone(),
+        config.refresh_interval,
+    );
+
+    let v1 = api_routes()
+        .route(
+            "/:network/tokens/:address/leaderboard",
+            get(token_leaderboard_handler),
+        )
+        .route(
+            "/:network/tokens/:token/traders/:address",
+            get(trader_handler),
+        )
+        .route("/:network/tokens/:token/pools", get(pools_handler));
     let app = Router::new()
         .route("/", get(health_check))
         .route("/health", get(health_check))
-        .route("/api/leaderboard", post(leaderboard_handler))
-        .layer(CorsLayer::permissive());
+        .nest("/api/v1", v1)
+        // Unversioned aliases kept for existing clients
+        .nest("/api", api_routes())
+        .layer(CorsLayer::permissive())
+        .with_state(state);
 
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
     println!("🚀 Server running on http://localhost:{}", port);
-    println!("🔗 API endpoint: http://localhost:{}/api/leaderboard", port);
+    println!(
+        "🔗 API endpoint: http://localhost:{}/api/v1/leaderboard",
+        port
+    );
+    println!(
+        "🔗 Token leaderboard: http://localhost:{}/api/v1/{{network}}/tokens/{{address}}/leaderboard",
+        port
+    );
+    println!(
+        "📡 Live updates: http://localhost:{}/api/v1/leaderboard/stream",
+        port
+    );
     println!("❤️  Health check: http://localhost:{}/health", port);
+    println!("🗄️  Leaderboard cache TTL: {}s", config.cache_ttl.as_secs());
+    println!(
+        "👀 Watching {} tokens, refreshed every {}s",
+        config.watchlist.len(),
+        config.refresh_interval.as_secs()
+    );
 
     axum::serve(listener, app).await?;
     Ok(())
 }
+
+/// Routes served both under `/api/v1` and, unversioned, under `/api`.
+fn api_routes() -> Router<AppState> {
+    Router::new()
+        .route("/leaderboard", post(leaderboard_handler))
+        .route("/leaderboard/stream", get(leaderboard_stream))
+        .route(
+            "/watchlist",
+            get(list_watchlist)
+                .post(add_to_watchlist)
+                .delete(remove_from_watchlist),
+        )
+}
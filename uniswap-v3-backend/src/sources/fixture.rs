@@ -0,0 +1,134 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use std::fs;
+use std::path::Path;
+
+use super::SwapSource;
+use crate::types::{PoolFilter, Swap, SwapWindow};
+
+/// Serves swaps from a JSON file holding an array of subgraph-shaped `Swap`
+/// objects. Used for tests and for replaying previously captured data offline.
+pub struct FixtureSwapSource {
+    swaps: Vec<Swap>,
+}
+
+impl FixtureSwapSource {
+    pub fn new(swaps: Vec<Swap>) -> Self {
+        Self { swaps }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read swap fixture {}: {}", path.display(), e))?;
+        let swaps: Vec<Swap> = serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("Failed to parse swap fixture {}: {}", path.display(), e))?;
+        Ok(Self::new(swaps))
+    }
+}
+
+#[async_trait]
+impl SwapSource for FixtureSwapSource {
+    fn name(&self) -> &'static str {
+        "fixture"
+    }
+
+    async fn latest_block(&self) -> Result<u64> {
+        self.swaps
+            .iter()
+            .filter_map(|swap| swap.transaction.block_number.parse().ok())
+            .max()
+            .ok_or_else(|| anyhow!("Swap fixture contains no swaps with a block number"))
+    }
+
+    async fn first_block_at(&self, timestamp: u64) -> Result<u64> {
+        let first = self
+            .swaps
+            .iter()
+            .filter(|swap| swap.timestamp.parse::<u64>().is_ok_and(|t| t >= timestamp))
+            .filter_map(|swap| swap.transaction.block_number.parse().ok())
+            .min();
+        match first {
+            Some(block) => Ok(block),
+            None => Ok(self.latest_block().await? + 1),
+        }
+    }
+
+    async fn fetch_swaps(
+        &self,
+        token_address: &str,
+        window: &SwapWindow,
+        pools: &PoolFilter,
+    ) -> Result<Vec<Swap>> {
+        let token = token_address.to_lowercase();
+
+        Ok(self
+            .swaps
+            .iter()
+            .filter(|swap| {
+                swap.pool.token_0.id.to_lowercase() == token
+                    || swap.pool.token_1.id.to_lowercase() == token
+            })
+            .filter(|swap| in_window(swap, window) && pools.matches(&swap.pool))
+            .cloned()
+            .collect())
+    }
+}
+
+fn in_window(swap: &Swap, window: &SwapWindow) -> bool {
+    let block = swap.transaction.block_number.parse::<u64>().ok();
+    let timestamp = swap.timestamp.parse::<u64>().ok();
+
+    let within = |value: Option<u64>, lower: Option<u64>, upper: Option<u64>| match value {
+        Some(value) => lower.is_none_or(|l| value >= l) && upper.is_none_or(|u| value <= u),
+        // Unparseable values can only match when that side is unbounded
+        None => lower.is_none() && upper.is_none(),
+    };
+
+    within(block, window.start_block, window.end_block)
+        && within(timestamp, window.from_timestamp, window.to_timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+
+    fn fixture() -> FixtureSwapSource {
+        FixtureSwapSource::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/swaps.json"))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fixture_filters_by_token_and_window() {
+        let source = fixture();
+        assert_eq!(source.latest_block().await.unwrap(), 19_000_040);
+
+        let all = source
+            .fetch_swaps(WETH, &SwapWindow::default(), &PoolFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 6);
+
+        let by_fee_tier = source
+            .fetch_swaps(WETH, &SwapWindow::default(), &PoolFilter::new(&[], &[3000]))
+            .await
+            .unwrap();
+        assert_eq!(by_fee_tier.len(), 2);
+
+        let window = SwapWindow {
+            start_block: Some(19_000_010),
+            to_timestamp: Some(1_705_000_300),
+            ..Default::default()
+        };
+        let ids: Vec<String> = source
+            .fetch_swaps(WETH, &window, &PoolFilter::default())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|swap| swap.id)
+            .collect();
+        assert_eq!(ids, vec!["0x02#1", "0x03#4"]);
+    }
+}
@@ -0,0 +1,139 @@
+mod fixture;
+mod rpc;
+mod stored;
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use clap::ValueEnum;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use crate::client::UniswapClient;
+use crate::config::Config;
+use crate::store::{SharedSwapStore, SwapStore};
+use crate::types::{PoolFilter, Swap, SwapWindow};
+use crate::utils::default_start_block;
+
+pub use fixture::FixtureSwapSource;
+pub use rpc::RpcSwapSource;
+pub use stored::StoredSwapSource;
+
+/// Anything that can produce the swaps touching a token inside a window.
+///
+/// Implementations return swaps in the same shape the subgraph does, so the
+/// aggregation code never needs to know where the data came from.
+#[async_trait]
+pub trait SwapSource: Send + Sync {
+    /// Short name shown in progress output
+    fn name(&self) -> &'static str;
+
+    /// Highest block this source can return swaps for
+    async fn latest_block(&self) -> Result<u64>;
+
+    /// Earliest block that can hold a swap at or after `timestamp`, or one
+    /// past the latest block when none can.
+    async fn first_block_at(&self, timestamp: u64) -> Result<u64>;
+
+    /// Swaps in `window` from the pools selected by `pools`. The filter is
+    /// applied before any `TARGET_SWAPS` cap, so the cap never starves a
+    /// selected pool in favour of busier unselected ones.
+    async fn fetch_swaps(
+        &self,
+        token_address: &str,
+        window: &SwapWindow,
+        pools: &PoolFilter,
+    ) -> Result<Vec<Swap>>;
+
+    /// Start block covering `lookback_blocks` before the latest block.
+    async fn default_start_block(&self, lookback_blocks: u64) -> Result<u64> {
+        let latest_block = self.latest_block().await?;
+        Ok(default_start_block(latest_block, lookback_blocks))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SourceKind {
+    /// Uniswap v3 subgraph on The Graph (or UNISWAP_SUBGRAPH_URL)
+    #[default]
+    Subgraph,
+    /// Pool `Swap` event logs read from a JSON-RPC endpoint
+    Rpc,
+    /// Swaps loaded from a local JSON file
+    Fixture,
+}
+
+impl FromStr for SourceKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        <Self as ValueEnum>::from_str(s, true).map_err(|_| {
+            anyhow!(
+                "Unsupported swap source: {}. Supported sources: subgraph, rpc, fixture",
+                s
+            )
+        })
+    }
+}
+
+/// Opens the swap store at `config.store_path`, when one is set, to be shared
+/// by every source passed it in `build_swap_source`.
+pub fn open_swap_store(config: &Config) -> Result<Option<SharedSwapStore>> {
+    config
+        .store_path
+        .as_ref()
+        .map(|path| Ok(Arc::new(Mutex::new(SwapStore::open(path)?))))
+        .transpose()
+}
+
+/// Builds the configured source, backed by `store` when there is one (see
+/// `open_swap_store`).
+pub fn build_swap_source(
+    kind: SourceKind,
+    network: &str,
+    config: &Config,
+    store: Option<&SharedSwapStore>,
+) -> Result<Box<dyn SwapSource>> {
+    let source = build_remote_source(kind, network, config)?;
+    match store {
+        Some(store) => {
+            let network_id = &config.networks.get(network)?.id;
+            Ok(Box::new(StoredSwapSource::new(
+                source,
+                store.clone(),
+                network_id,
+                config.target_swaps,
+            )))
+        }
+        None => Ok(source),
+    }
+}
+
+fn build_remote_source(
+    kind: SourceKind,
+    network: &str,
+    config: &Config,
+) -> Result<Box<dyn SwapSource>> {
+    match kind {
+        SourceKind::Subgraph => Ok(Box::new(UniswapClient::new(network, config)?)),
+        SourceKind::Rpc => {
+            let rpc_url = config
+                .rpc_url
+                .as_deref()
+                .or(config.networks.get(network)?.rpc_url.as_deref())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "The rpc swap source needs an endpoint. Set RPC_URL, pass --rpc-url or add rpc_url to the network"
+                    )
+                })?;
+            Ok(Box::new(RpcSwapSource::new(network, rpc_url, config)?))
+        }
+        SourceKind::Fixture => {
+            let path = config.fixture_path.as_deref().ok_or_else(|| {
+                anyhow!(
+                    "The fixture swap source needs a file. Set SWAP_FIXTURE_PATH or pass --fixture"
+                )
+            })?;
+            Ok(Box::new(FixtureSwapSource::from_file(path)?))
+        }
+    }
+}
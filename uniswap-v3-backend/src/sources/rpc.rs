@@ -0,0 +1,509 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use ethers::contract::{EthEvent, abigen, parse_log};
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::{Address, Filter, H256, I256, Log, ValueOrArray};
+use ethers::utils::format_units;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::SwapSource;
+use crate::config::Config;
+use crate::types::{Pool, PoolFilter, Swap, SwapWindow, Token, Transaction};
+
+abigen!(
+    UniswapV3Factory,
+    r#"[
+        event PoolCreated(address indexed token0, address indexed token1, uint24 indexed fee, int24 tickSpacing, address pool)
+    ]"#
+);
+
+abigen!(
+    UniswapV3Pool,
+    r#"[
+        event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick)
+    ]"#
+);
+
+abigen!(
+    Erc20,
+    r#"[
+        function symbol() external view returns (string)
+        function name() external view returns (string)
+        function decimals() external view returns (uint8)
+    ]"#
+);
+
+/// Symbols treated as $1 when estimating `amountUSD`, which logs don't carry
+const USD_STABLECOINS: &[&str] = &[
+    "USDC", "USDC.E", "USDBC", "USDT", "USDT0", "DAI", "FRAX", "LUSD", "BUSD", "TUSD", "USDP",
+];
+
+/// Most pool addresses passed to a single `eth_getLogs` call
+const MAX_ADDRESSES_PER_QUERY: usize = 500;
+
+/// Smallest block span we split a failing `eth_getLogs` range into
+const MIN_LOG_RANGE: u64 = 16;
+
+/// Decodes Uniswap v3 pool `Swap` events straight from a JSON-RPC node.
+///
+/// Pools are discovered from the factory's `PoolCreated` events, and amounts
+/// are scaled by each token's `decimals()`. Logs carry no USD pricing, so
+/// `amountUSD` is only filled in when the other side of the pool is a known
+/// USD stablecoin and is `0` otherwise.
+pub struct RpcSwapSource {
+    provider: Arc<Provider<Http>>,
+    factory: Address,
+    factory_start_block: u64,
+    /// Chain id the network's registry entry expects the endpoint to serve
+    chain_id: u64,
+    network: String,
+    log_range: u64,
+    tokens: Mutex<HashMap<Address, Token>>,
+    block_timestamps: Mutex<HashMap<u64, u64>>,
+    /// `tx.from` of the transactions we've decoded swaps from
+    tx_senders: Mutex<HashMap<H256, Address>>,
+}
+
+impl RpcSwapSource {
+    pub fn new(network: &str, rpc_url: &str, config: &Config) -> Result<Self> {
+        let network_config = config.networks.get(network)?;
+        let provider = Provider::<Http>::try_from(rpc_url)
+            .map_err(|e| anyhow!("Invalid RPC URL '{}': {}", rpc_url, e))?;
+
+        let factory = config
+            .factory_address
+            .as_deref()
+            .unwrap_or(&network_config.factory_address)
+            .parse::<Address>()
+            .map_err(|e| anyhow!("Invalid Uniswap v3 factory address: {}", e))?;
+
+        eprintln!(
+            "Using {} network via JSON-RPC ({})",
+            network_config.name, rpc_url
+        );
+
+        Ok(Self {
+            provider: Arc::new(provider),
+            factory,
+            factory_start_block: network_config.factory_start_block,
+            chain_id: network_config.chain_id,
+            network: network_config.id.clone(),
+            log_range: config.rpc_log_range,
+            tokens: Mutex::new(HashMap::new()),
+            block_timestamps: Mutex::new(HashMap::new()),
+            tx_senders: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Warns when the endpoint serves a different chain than the network's
+    /// registry entry, e.g. an `RPC_URL` left pointing at another network.
+    async fn check_chain_id(&self) -> Result<()> {
+        let chain_id = self.provider.get_chainid().await?.as_u64();
+        if chain_id != self.chain_id {
+            eprintln!(
+                "⚠️  RPC endpoint reports chain id {}, but network {} is chain {}",
+                chain_id, self.network, self.chain_id
+            );
+        }
+        Ok(())
+    }
+
+    /// Every pool in which `token` is either side, from the factory's logs.
+    async fn discover_pools(&self, token: Address, to_block: u64) -> Result<Vec<PoolInfo>> {
+        let base = Filter::new()
+            .address(self.factory)
+            .event(&PoolCreatedFilter::abi_signature());
+
+        let mut logs = self
+            .get_logs(
+                base.clone().topic1(token),
+                self.factory_start_block,
+                to_block,
+            )
+            .await?;
+        logs.extend(
+            self.get_logs(base.topic2(token), self.factory_start_block, to_block)
+                .await?,
+        );
+
+        let mut pools = Vec::with_capacity(logs.len());
+        for log in logs {
+            let event: PoolCreatedFilter = parse_log(log)?;
+            pools.push(PoolInfo {
+                address: event.pool,
+                fee: event.fee,
+                token_0: self.token(event.token_0).await?,
+                token_1: self.token(event.token_1).await?,
+            });
+        }
+        Ok(pools)
+    }
+
+    /// `eth_getLogs` over `[from, to]`, halving the range whenever the node
+    /// rejects it (too many results or too wide a span) until it succeeds.
+    async fn get_logs(&self, filter: Filter, from: u64, to: u64) -> Result<Vec<Log>> {
+        let mut logs = Vec::new();
+        let mut ranges = Vec::new();
+
+        let mut start = from;
+        while start <= to {
+            let end = to.min(start.saturating_add(self.log_range - 1));
+            ranges.push((start, end));
+            start = end + 1;
+        }
+        ranges.reverse();
+
+        while let Some((start, end)) = ranges.pop() {
+            let range_filter = filter.clone().from_block(start).to_block(end);
+            match self.provider.get_logs(&range_filter).await {
+                Ok(batch) => logs.extend(batch),
+                Err(e) if end - start >= MIN_LOG_RANGE => {
+                    let mid = start + (end - start) / 2;
+                    eprintln!(
+                        "eth_getLogs failed for blocks {}..={} ({}), splitting range",
+                        start, end, e
+                    );
+                    ranges.push((mid + 1, end));
+                    ranges.push((start, mid));
+                }
+                Err(e) => {
+                    return Err(anyhow!(
+                        "eth_getLogs failed for blocks {}..={}: {}",
+                        start,
+                        end,
+                        e
+                    ));
+                }
+            }
+        }
+
+        Ok(logs)
+    }
+
+    async fn token(&self, address: Address) -> Result<Token> {
+        if let Some(token) = self.tokens.lock().await.get(&address) {
+            return Ok(token.clone());
+        }
+
+        let contract = Erc20::new(address, self.provider.clone());
+        let decimals = contract
+            .decimals()
+            .call()
+            .await
+            .map_err(|e| anyhow!("Failed to read decimals() of {:?}: {}", address, e))?;
+        // Some older tokens return bytes32 for these, so they are best-effort
+        let symbol = contract
+            .symbol()
+            .call()
+            .await
+            .unwrap_or_else(|_| "UNKNOWN".to_string());
+        let name = contract
+            .name()
+            .call()
+            .await
+            .unwrap_or_else(|_| "Unknown".to_string());
+
+        let token = Token {
+            id: format!("{:?}", address),
+            symbol,
+            name,
+            decimals: decimals.to_string(),
+        };
+        self.tokens.lock().await.insert(address, token.clone());
+        Ok(token)
+    }
+
+    async fn block_timestamp(&self, block: u64) -> Result<u64> {
+        if let Some(timestamp) = self.block_timestamps.lock().await.get(&block) {
+            return Ok(*timestamp);
+        }
+
+        let timestamp = self
+            .provider
+            .get_block(block)
+            .await?
+            .ok_or_else(|| anyhow!("Block {} not found", block))?
+            .timestamp
+            .as_u64();
+        self.block_timestamps.lock().await.insert(block, timestamp);
+        Ok(timestamp)
+    }
+
+    /// Sender of `tx_hash`, looked up once per transaction however many swaps
+    /// it made. `None` when the node doesn't know the transaction.
+    async fn transaction_origin(&self, tx_hash: H256) -> Result<Option<Address>> {
+        if let Some(sender) = self.tx_senders.lock().await.get(&tx_hash) {
+            return Ok(Some(*sender));
+        }
+
+        let Some(tx) = self.provider.get_transaction(tx_hash).await? else {
+            return Ok(None);
+        };
+        self.tx_senders.lock().await.insert(tx_hash, tx.from);
+        Ok(Some(tx.from))
+    }
+
+    /// First block in `[low, high]` whose timestamp is at least `timestamp`,
+    /// or `high + 1` if there is none.
+    async fn first_block_at_or_after(
+        &self,
+        timestamp: u64,
+        mut low: u64,
+        mut high: u64,
+    ) -> Result<u64> {
+        let mut result = high + 1;
+        while low <= high {
+            let mid = low + (high - low) / 2;
+            if self.block_timestamp(mid).await? >= timestamp {
+                result = mid;
+                if mid == 0 {
+                    break;
+                }
+                high = mid - 1;
+            } else {
+                low = mid + 1;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Narrows the window's block bounds using its timestamp bounds, so log
+    /// queries never scan blocks outside the requested time range.
+    async fn block_range(&self, window: &SwapWindow, latest_block: u64) -> Result<(u64, u64)> {
+        let mut from = window.start_block.unwrap_or(self.factory_start_block);
+        let mut to = window.end_block.unwrap_or(latest_block).min(latest_block);
+
+        if from > to {
+            return Ok((from, to));
+        }
+        if let Some(from_timestamp) = window.from_timestamp {
+            from = self
+                .first_block_at_or_after(from_timestamp, from, to)
+                .await?;
+        }
+        if let Some(to_timestamp) = window.to_timestamp
+            && from <= to
+        {
+            let after = self
+                .first_block_at_or_after(to_timestamp.saturating_add(1), from, to)
+                .await?;
+            to = after.saturating_sub(1);
+        }
+        Ok((from, to))
+    }
+
+    async fn decode_swap(&self, log: Log, pool: &PoolInfo) -> Result<Swap> {
+        let block_number = log
+            .block_number
+            .ok_or_else(|| anyhow!("Swap log is missing its block number"))?
+            .as_u64();
+        let tx_hash = log
+            .transaction_hash
+            .ok_or_else(|| anyhow!("Swap log is missing its transaction hash"))?;
+        let log_index = log.log_index.unwrap_or_default();
+        let event: SwapFilter = parse_log(log)?;
+
+        let amount_0 = format_token_amount(event.amount_0, &pool.token_0)?;
+        let amount_1 = format_token_amount(event.amount_1, &pool.token_1)?;
+        let amount_usd = estimate_usd(&pool.token_0, &amount_0, &pool.token_1, &amount_1);
+        let origin = self.transaction_origin(tx_hash).await?;
+
+        Ok(Swap {
+            id: format!("{:?}#{}", tx_hash, log_index),
+            timestamp: self.block_timestamp(block_number).await?.to_string(),
+            sender: format!("{:?}", event.sender),
+            recipient: format!("{:?}", event.recipient),
+            origin: origin.map(|address| format!("{:?}", address)),
+            amount_0,
+            amount_1,
+            amount_usd: amount_usd.to_string(),
+            log_index: Some(log_index.to_string()),
+            pool: Pool {
+                id: format!("{:?}", pool.address),
+                token_0: pool.token_0.clone(),
+                token_1: pool.token_1.clone(),
+                fee_tier: Some(pool.fee.to_string()),
+                tick: Some(event.tick.to_string()),
+                sqrt_price: event.sqrt_price_x96.to_string(),
+                total_value_locked_usd: None,
+            },
+            transaction: Transaction {
+                block_number: block_number.to_string(),
+            },
+        })
+    }
+}
+
+#[async_trait]
+impl SwapSource for RpcSwapSource {
+    fn name(&self) -> &'static str {
+        "json-rpc"
+    }
+
+    async fn latest_block(&self) -> Result<u64> {
+        Ok(self.provider.get_block_number().await?.as_u64())
+    }
+
+    async fn first_block_at(&self, timestamp: u64) -> Result<u64> {
+        let latest_block = self.latest_block().await?;
+        self.first_block_at_or_after(timestamp, self.factory_start_block, latest_block)
+            .await
+    }
+
+    async fn fetch_swaps(
+        &self,
+        token_address: &str,
+        window: &SwapWindow,
+        selected: &PoolFilter,
+    ) -> Result<Vec<Swap>> {
+        let token = token_address
+            .parse::<Address>()
+            .map_err(|_| anyhow!("Invalid token address format: {}", token_address))?;
+
+        self.check_chain_id().await?;
+        let latest_block = self.latest_block().await?;
+        let (from, to) = self.block_range(window, latest_block).await?;
+        if from > to {
+            return Ok(vec![]);
+        }
+
+        let mut pools = self.discover_pools(token, to).await?;
+        eprintln!(
+            "Found {} Uniswap v3 pools for token {}",
+            pools.len(),
+            token_address
+        );
+        // Only the selected pools' logs are requested
+        if !selected.is_empty() {
+            pools.retain(|pool| {
+                selected.matches_pool(&format!("{:?}", pool.address), Some(pool.fee))
+            });
+            eprintln!("Reading logs from {} selected pools", pools.len());
+        }
+        let pools_by_address: HashMap<Address, &PoolInfo> =
+            pools.iter().map(|pool| (pool.address, pool)).collect();
+
+        let mut swaps = Vec::new();
+        for chunk in pools.chunks(MAX_ADDRESSES_PER_QUERY) {
+            let addresses: Vec<Address> = chunk.iter().map(|pool| pool.address).collect();
+            let filter = Filter::new()
+                .address(ValueOrArray::Array(addresses))
+                .event(&SwapFilter::abi_signature());
+
+            let before = swaps.len();
+            for log in self.get_logs(filter, from, to).await? {
+                let Some(pool) = pools_by_address.get(&log.address) else {
+                    continue;
+                };
+                swaps.push(self.decode_swap(log, pool).await?);
+            }
+            eprintln!(
+                "Fetched {} swaps (total: {})",
+                swaps.len() - before,
+                swaps.len()
+            );
+        }
+
+        // Match the subgraph's newest-first ordering
+        swaps.sort_by(|a, b| {
+            let key = |swap: &Swap| {
+                (
+                    swap.transaction.block_number.parse::<u64>().unwrap_or(0),
+                    swap.id.clone(),
+                )
+            };
+            key(b).cmp(&key(a))
+        });
+
+        eprintln!(
+            "Total swaps fetched: {} (blocks {} to {})",
+            swaps.len(),
+            from,
+            to
+        );
+        Ok(swaps)
+    }
+}
+
+struct PoolInfo {
+    address: Address,
+    fee: u32,
+    token_0: Token,
+    token_1: Token,
+}
+
+/// Formats a raw signed token amount as a decimal string in whole-token units.
+fn format_token_amount(amount: I256, token: &Token) -> Result<String> {
+    let decimals: u32 = token
+        .decimals
+        .parse()
+        .map_err(|_| anyhow!("Invalid decimals for token {}", token.id))?;
+    let formatted = format_units(amount, decimals)
+        .map_err(|e| anyhow!("Failed to format amount {}: {}", amount, e))?;
+
+    if !formatted.contains('.') {
+        return Ok(formatted);
+    }
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    Ok(match trimmed {
+        "" | "-" => "0".to_string(),
+        _ => trimmed.to_string(),
+    })
+}
+
+/// USD value of a swap when one side is a stablecoin, otherwise zero.
+fn estimate_usd(token_0: &Token, amount_0: &str, token_1: &Token, amount_1: &str) -> Decimal {
+    let is_stable = |token: &Token| USD_STABLECOINS.contains(&token.symbol.to_uppercase().as_str());
+    let amount = if is_stable(token_0) {
+        amount_0
+    } else if is_stable(token_1) {
+        amount_1
+    } else {
+        return Decimal::ZERO;
+    };
+    amount
+        .parse::<Decimal>()
+        .map(|value| value.abs())
+        .unwrap_or(Decimal::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(symbol: &str, decimals: &str) -> Token {
+        Token {
+            id: "0x0000000000000000000000000000000000000001".to_string(),
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+            decimals: decimals.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_format_token_amount_scales_by_decimals() {
+        let weth = token("WETH", "18");
+        let amount = I256::from_dec_str("-1500000000000000000").unwrap();
+        assert_eq!(format_token_amount(amount, &weth).unwrap(), "-1.5");
+
+        let usdc = token("USDC", "6");
+        let amount = I256::from_dec_str("3750000000").unwrap();
+        assert_eq!(format_token_amount(amount, &usdc).unwrap(), "3750");
+        assert_eq!(format_token_amount(I256::zero(), &usdc).unwrap(), "0");
+    }
+
+    #[test]
+    fn test_estimate_usd_uses_stablecoin_leg() {
+        let usdc = token("USDC", "6");
+        let weth = token("WETH", "18");
+        let pepe = token("PEPE", "18");
+        assert_eq!(
+            estimate_usd(&usdc, "-3750", &weth, "1.5"),
+            Decimal::from(3750)
+        );
+        assert_eq!(estimate_usd(&pepe, "-10", &weth, "1.5"), Decimal::ZERO);
+    }
+}
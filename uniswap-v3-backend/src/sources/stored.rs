@@ -0,0 +1,282 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use std::sync::MutexGuard;
+
+use super::SwapSource;
+use crate::store::{SharedSwapStore, SwapStore, plan_sync};
+use crate::types::{PoolFilter, Swap, SwapWindow};
+
+/// Wraps another source with a local `SwapStore`. Block ranges already synced
+/// for a token are served from disk; only the blocks outside them are fetched
+/// from the wrapped source.
+pub struct StoredSwapSource {
+    inner: Box<dyn SwapSource>,
+    store: SharedSwapStore,
+    network: String,
+    /// `TARGET_SWAPS`; a fetch that hits it only covers the blocks it reached
+    swap_cap: Option<usize>,
+}
+
+impl StoredSwapSource {
+    pub fn new(
+        inner: Box<dyn SwapSource>,
+        store: SharedSwapStore,
+        network: &str,
+        swap_cap: Option<usize>,
+    ) -> Self {
+        Self {
+            inner,
+            store,
+            network: network.to_string(),
+            swap_cap,
+        }
+    }
+
+    fn store(&self) -> Result<MutexGuard<'_, SwapStore>> {
+        self.store
+            .lock()
+            .map_err(|_| anyhow!("Swap store lock poisoned"))
+    }
+}
+
+#[async_trait]
+impl SwapSource for StoredSwapSource {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn latest_block(&self) -> Result<u64> {
+        self.inner.latest_block().await
+    }
+
+    async fn first_block_at(&self, timestamp: u64) -> Result<u64> {
+        self.inner.first_block_at(timestamp).await
+    }
+
+    async fn fetch_swaps(
+        &self,
+        token_address: &str,
+        window: &SwapWindow,
+        pools: &PoolFilter,
+    ) -> Result<Vec<Swap>> {
+        let token = token_address.to_lowercase();
+
+        // Synced ranges cover every pool of the token, so a filtered fetch
+        // can't extend them; it goes straight to the wrapped source
+        if !pools.is_empty() {
+            return self.inner.fetch_swaps(&token, window, pools).await;
+        }
+
+        // Coverage is tracked in blocks, so time bounds are resolved to the
+        // blocks that cover them. A window with neither can't be served
+        // incrementally; fetch it and keep the swaps anyway.
+        let start_block = match (window.start_block, window.from_timestamp) {
+            (Some(start_block), _) => start_block,
+            (None, Some(from)) => self.inner.first_block_at(from).await?,
+            (None, None) => {
+                let swaps = self.inner.fetch_swaps(&token, window, pools).await?;
+                self.store()?.insert_swaps(&self.network, &swaps)?;
+                return Ok(swaps);
+            }
+        };
+        let end_block = match (window.end_block, window.to_timestamp) {
+            (Some(end_block), _) => end_block,
+            (None, Some(to)) => self
+                .inner
+                .first_block_at(to.saturating_add(1))
+                .await?
+                .saturating_sub(1),
+            (None, None) => self.inner.latest_block().await?,
+        };
+        if start_block > end_block {
+            return Ok(Vec::new());
+        }
+
+        let synced = self.store()?.sync_range(&self.network, &token)?;
+        let (missing, _) = plan_sync(synced, start_block, end_block);
+        match synced {
+            Some(range) => eprintln!(
+                "💾 Swap store has blocks {}-{}; fetching {} missing range(s)",
+                range.from_block,
+                range.to_block,
+                missing.len()
+            ),
+            None => eprintln!("💾 Swap store has no history for this token yet"),
+        }
+
+        // Record progress after each range so a failure keeps what was fetched
+        let mut covered = synced;
+        for (from_block, to_block) in missing {
+            let range_window = SwapWindow {
+                start_block: Some(from_block),
+                end_block: Some(to_block),
+                ..Default::default()
+            };
+            let swaps = self.inner.fetch_swaps(&token, &range_window, pools).await?;
+
+            // Capped fetches keep the newest swaps, so only the blocks after
+            // the oldest one returned are known to be complete
+            let complete_from = match self.swap_cap {
+                Some(cap) if swaps.len() >= cap => swaps
+                    .iter()
+                    .filter_map(|swap| swap.transaction.block_number.parse::<u64>().ok())
+                    .min()
+                    .map_or(from_block, |block| block + 1),
+                _ => from_block,
+            };
+
+            let mut store = self.store()?;
+            store.insert_swaps(&self.network, &swaps)?;
+            if complete_from > to_block {
+                continue;
+            }
+            let (_, merged) = plan_sync(covered, complete_from, to_block);
+            // A capped fetch that stopped short of the synced range can't be
+            // merged with it; replacing it would forget blocks already synced
+            let replaces = covered.is_some_and(|range| {
+                merged.from_block > range.from_block || merged.to_block < range.to_block
+            });
+            if complete_from > from_block && replaces {
+                continue;
+            }
+            store.set_sync_range(&self.network, &token, merged)?;
+            covered = Some(merged);
+        }
+
+        let stored_window = SwapWindow {
+            end_block: Some(end_block),
+            ..window.clone()
+        };
+        self.store()?
+            .load_swaps(&self.network, &token, &stored_window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::FixtureSwapSource;
+    use crate::store::SyncRange;
+    use std::sync::{Arc, Mutex};
+
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+
+    #[tokio::test]
+    async fn test_time_windows_sync_incrementally() {
+        let path = std::env::temp_dir().join(format!("swap-store-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let fixture = FixtureSwapSource::from_file(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fixtures/swaps.json"
+        ))
+        .unwrap();
+        let store = Arc::new(Mutex::new(SwapStore::open(&path).unwrap()));
+        let source = StoredSwapSource::new(Box::new(fixture), store, "ethereum", None);
+        let synced = || {
+            source
+                .store()
+                .unwrap()
+                .sync_range("ethereum", WETH)
+                .unwrap()
+        };
+
+        let window = SwapWindow {
+            from_timestamp: Some(1_705_000_120),
+            to_timestamp: Some(1_705_000_300),
+            ..Default::default()
+        };
+        let ids: Vec<String> = source
+            .fetch_swaps(WETH, &window, &PoolFilter::default())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|swap| swap.id)
+            .collect();
+        assert_eq!(ids, vec!["0x03#4", "0x02#1"]);
+        // Up to the block before the first swap after the window
+        assert_eq!(
+            synced(),
+            Some(SyncRange {
+                from_block: 19_000_010,
+                to_block: 19_000_029
+            })
+        );
+
+        // An open-ended window only extends the synced range
+        let since = SwapWindow {
+            from_timestamp: Some(1_705_000_120),
+            ..Default::default()
+        };
+        assert_eq!(
+            source
+                .fetch_swaps(WETH, &since, &PoolFilter::default())
+                .await
+                .unwrap()
+                .len(),
+            5
+        );
+        assert_eq!(
+            synced(),
+            Some(SyncRange {
+                from_block: 19_000_010,
+                to_block: 19_000_040
+            })
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_capped_fetches_keep_non_adjacent_coverage() {
+        let path =
+            std::env::temp_dir().join(format!("swap-store-capped-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let fixture = FixtureSwapSource::from_file(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fixtures/swaps.json"
+        ))
+        .unwrap();
+        let store = Arc::new(Mutex::new(SwapStore::open(&path).unwrap()));
+        let source = StoredSwapSource::new(Box::new(fixture), store, "ethereum", Some(3));
+        let blocks = |start_block, end_block| SwapWindow {
+            start_block: Some(start_block),
+            end_block: Some(end_block),
+            ..Default::default()
+        };
+        let synced = || {
+            source
+                .store()
+                .unwrap()
+                .sync_range("ethereum", WETH)
+                .unwrap()
+        };
+
+        source
+            .fetch_swaps(
+                WETH,
+                &blocks(19_000_000, 19_000_012),
+                &PoolFilter::default(),
+            )
+            .await
+            .unwrap();
+        let first = SyncRange {
+            from_block: 19_000_000,
+            to_block: 19_000_012,
+        };
+        assert_eq!(synced(), Some(first));
+
+        // Blocks 19_000_013-040 hold five swaps, so the fetch hits the cap and
+        // is only complete after block 19_000_020, which leaves a gap
+        source
+            .fetch_swaps(
+                WETH,
+                &blocks(19_000_005, 19_000_040),
+                &PoolFilter::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(synced(), Some(first));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::{Result, anyhow};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::types::{Swap, SwapWindow};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS swaps (
+        network      TEXT    NOT NULL,
+        pool         TEXT    NOT NULL,
+        id           TEXT    NOT NULL,
+        token0       TEXT    NOT NULL,
+        token1       TEXT    NOT NULL,
+        block_number INTEGER NOT NULL,
+        timestamp    INTEGER NOT NULL,
+        data         TEXT    NOT NULL,
+        PRIMARY KEY (network, pool, id)
+    );
+    CREATE INDEX IF NOT EXISTS swaps_token0 ON swaps (network, token0, block_number);
+    CREATE INDEX IF NOT EXISTS swaps_token1 ON swaps (network, token1, block_number);
+
+    CREATE TABLE IF NOT EXISTS sync_state (
+        network    TEXT    NOT NULL,
+        token      TEXT    NOT NULL,
+        from_block INTEGER NOT NULL,
+        to_block   INTEGER NOT NULL,
+        PRIMARY KEY (network, token)
+    );
+";
+
+/// Inclusive block range whose swaps for a token are fully stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncRange {
+    pub from_block: u64,
+    pub to_block: u64,
+}
+
+/// SQLite-backed store of swaps keyed by network, pool and swap id, plus the
+/// block range already synced per token.
+pub struct SwapStore {
+    conn: Connection,
+}
+
+/// One open store shared by every source built from the same config.
+pub type SharedSwapStore = Arc<Mutex<SwapStore>>;
+
+impl SwapStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                anyhow!(
+                    "Failed to create swap store directory {}: {}",
+                    parent.display(),
+                    e
+                )
+            })?;
+        }
+        let conn = Connection::open(path)
+            .map_err(|e| anyhow!("Failed to open swap store {}: {}", path.display(), e))?;
+        Self::init(conn)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| anyhow!("Failed to initialize swap store: {}", e))?;
+        Ok(Self { conn })
+    }
+
+    /// Inserts or replaces `swaps`, returning how many rows were written.
+    pub fn insert_swaps(&mut self, network: &str, swaps: &[Swap]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO swaps
+                    (network, pool, id, token0, token1, block_number, timestamp, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for swap in swaps {
+                stmt.execute(params![
+                    network,
+                    swap.pool.id.to_lowercase(),
+                    swap.id,
+                    swap.pool.token_0.id.to_lowercase(),
+                    swap.pool.token_1.id.to_lowercase(),
+                    parse_column(&swap.transaction.block_number, "block number", &swap.id)?,
+                    parse_column(&swap.timestamp, "timestamp", &swap.id)?,
+                    serde_json::to_string(swap)?,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(swaps.len())
+    }
+
+    /// Stored swaps touching `token` inside `window`, newest first.
+    pub fn load_swaps(&self, network: &str, token: &str, window: &SwapWindow) -> Result<Vec<Swap>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT data FROM swaps
+             WHERE network = ?1 AND (token0 = ?2 OR token1 = ?2)
+               AND block_number >= ?3 AND block_number <= ?4
+               AND timestamp >= ?5 AND timestamp <= ?6
+             ORDER BY timestamp DESC, id DESC",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                network,
+                token.to_lowercase(),
+                window.start_block.unwrap_or(0) as i64,
+                window.end_block.map_or(i64::MAX, |b| b as i64),
+                window.from_timestamp.unwrap_or(0) as i64,
+                window.to_timestamp.map_or(i64::MAX, |t| t as i64),
+            ],
+            |row| row.get::<_, String>(0),
+        )?;
+
+        rows.map(|data| {
+            let data = data?;
+            serde_json::from_str(&data).map_err(|e| anyhow!("Corrupt swap in store: {}", e))
+        })
+        .collect()
+    }
+
+    pub fn sync_range(&self, network: &str, token: &str) -> Result<Option<SyncRange>> {
+        let range = self
+            .conn
+            .query_row(
+                "SELECT from_block, to_block FROM sync_state WHERE network = ?1 AND token = ?2",
+                params![network, token.to_lowercase()],
+                |row| {
+                    Ok(SyncRange {
+                        from_block: row.get::<_, i64>(0)? as u64,
+                        to_block: row.get::<_, i64>(1)? as u64,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(range)
+    }
+
+    pub fn set_sync_range(&self, network: &str, token: &str, range: SyncRange) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO sync_state (network, token, from_block, to_block)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                network,
+                token.to_lowercase(),
+                range.from_block as i64,
+                range.to_block as i64
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn parse_column(value: &str, column: &str, swap_id: &str) -> Result<i64> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("Swap {} has an invalid {}: {}", swap_id, column, value))
+}
+
+/// Block ranges that must be fetched to cover `start..=end` given what is
+/// already synced, and the synced range afterwards. A request that doesn't
+/// touch or adjoin the synced range replaces it rather than leaving a gap.
+pub fn plan_sync(synced: Option<SyncRange>, start: u64, end: u64) -> (Vec<(u64, u64)>, SyncRange) {
+    let requested = SyncRange {
+        from_block: start,
+        to_block: end,
+    };
+
+    match synced {
+        Some(range)
+            if start <= range.to_block.saturating_add(1)
+                && end.saturating_add(1) >= range.from_block =>
+        {
+            let mut missing = Vec::new();
+            if start < range.from_block {
+                missing.push((start, range.from_block - 1));
+            }
+            if end > range.to_block {
+                missing.push((range.to_block + 1, end));
+            }
+            let merged = SyncRange {
+                from_block: start.min(range.from_block),
+                to_block: end.max(range.to_block),
+            };
+            (missing, merged)
+        }
+        _ => (vec![(start, end)], requested),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+
+    #[test]
+    fn test_plan_sync_fetches_only_missing_ranges() {
+        let synced = Some(SyncRange {
+            from_block: 100,
+            to_block: 200,
+        });
+
+        assert_eq!(plan_sync(synced, 150, 180).0, vec![]);
+        assert_eq!(
+            plan_sync(synced, 50, 250),
+            (
+                vec![(50, 99), (201, 250)],
+                SyncRange {
+                    from_block: 50,
+                    to_block: 250
+                }
+            )
+        );
+        // Disjoint requests start a fresh range
+        assert_eq!(plan_sync(synced, 300, 400).0, vec![(300, 400)]);
+        assert_eq!(plan_sync(None, 1, 2).0, vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_store_round_trips_swaps_by_token_and_window() {
+        let swaps: Vec<Swap> =
+            serde_json::from_str(include_str!("../fixtures/swaps.json")).unwrap();
+        let mut store = SwapStore::open_in_memory().unwrap();
+        store.insert_swaps("ethereum", &swaps).unwrap();
+        // Re-inserting is idempotent
+        store.insert_swaps("ethereum", &swaps).unwrap();
+
+        let all = store
+            .load_swaps("ethereum", WETH, &SwapWindow::default())
+            .unwrap();
+        assert_eq!(all.len(), 6);
+        assert!(all.windows(2).all(|w| w[0].timestamp >= w[1].timestamp));
+
+        let window = SwapWindow {
+            start_block: Some(19_000_010),
+            to_timestamp: Some(1_705_000_300),
+            ..Default::default()
+        };
+        let ids: Vec<String> = store
+            .load_swaps("ethereum", WETH, &window)
+            .unwrap()
+            .into_iter()
+            .map(|swap| swap.id)
+            .collect();
+        assert_eq!(ids, vec!["0x03#4", "0x02#1"]);
+        assert!(store.load_swaps("base", WETH, &window).unwrap().is_empty());
+    }
+}
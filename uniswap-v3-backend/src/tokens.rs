@@ -0,0 +1,171 @@
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::networks::{NetworkConfig, NetworkRegistry};
+use crate::utils::{is_valid_address, load_registry_file, parse_toml};
+
+/// Per-network token addresses shipped with the binary
+const BUNDLED_TOKENS: &str = include_str!("../tokens/token_map.toml");
+
+#[derive(Debug, Deserialize)]
+struct TokenMapFile {
+    #[serde(default)]
+    tokens: Vec<TokenEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenEntry {
+    symbol: String,
+    /// Network id to the token's address on that network
+    addresses: BTreeMap<String, String>,
+}
+
+/// Where one token lives on one network.
+#[derive(Debug)]
+pub struct ChainToken {
+    pub network: NetworkConfig,
+    /// Lowercase token address on `network`
+    pub address: String,
+}
+
+/// The same token across networks, built from the bundled map and an
+/// optional user file. User entries add to or override the bundled addresses
+/// of the token with the same symbol.
+#[derive(Debug, Default)]
+pub struct TokenMap {
+    /// Uppercase symbol to network id to lowercase address
+    tokens: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl TokenMap {
+    /// Network names in the files are resolved through `networks`.
+    pub fn load(user_file: Option<&str>, networks: &NetworkRegistry) -> Result<Self> {
+        let mut map = Self::default();
+        map.merge(parse_toml(BUNDLED_TOKENS, "bundled token map")?, networks)?;
+
+        if let Some(path) = user_file {
+            map.merge(load_registry_file(Path::new(path), "token map")?, networks)?;
+        }
+
+        Ok(map)
+    }
+
+    /// Addresses of `token` (a symbol, or its address on any mapped network)
+    /// on each of `networks`. `all` selects every registered network the token
+    /// has an address on; naming a network it has none on is an error.
+    pub fn resolve(
+        &self,
+        token: &str,
+        networks: &[String],
+        registry: &NetworkRegistry,
+    ) -> Result<Vec<ChainToken>> {
+        let (symbol, addresses) = self.find(token).ok_or_else(|| {
+            anyhow!(
+                "Token {} is not in the token map; add its per-network addresses to TOKEN_MAP_PATH",
+                token
+            )
+        })?;
+
+        let ids: Vec<&str> = if networks.iter().any(|n| n.eq_ignore_ascii_case("all")) {
+            registry
+                .ids()
+                .filter(|id| addresses.contains_key(*id))
+                .collect()
+        } else {
+            networks
+                .iter()
+                .map(|network| registry.get(network).map(|config| config.id.as_str()))
+                .collect::<Result<_>>()?
+        };
+
+        let mut resolved: Vec<ChainToken> = Vec::new();
+        for id in ids {
+            if resolved.iter().any(|chain| chain.network.id == id) {
+                continue;
+            }
+            let address = addresses
+                .get(id)
+                .ok_or_else(|| anyhow!("Token map has no {} address on {}", symbol, id))?;
+            resolved.push(ChainToken {
+                network: registry.get(id)?.clone(),
+                address: address.clone(),
+            });
+        }
+
+        if resolved.is_empty() {
+            return Err(anyhow!(
+                "Token map has no {} address on any network",
+                symbol
+            ));
+        }
+        Ok(resolved)
+    }
+
+    fn find(&self, token: &str) -> Option<(&str, &BTreeMap<String, String>)> {
+        let token = token.to_lowercase();
+        self.tokens
+            .iter()
+            .find(|(symbol, addresses)| {
+                symbol.eq_ignore_ascii_case(&token) || addresses.values().any(|a| *a == token)
+            })
+            .map(|(symbol, addresses)| (symbol.as_str(), addresses))
+    }
+
+    fn merge(&mut self, file: TokenMapFile, networks: &NetworkRegistry) -> Result<()> {
+        for entry in file.tokens {
+            let addresses = self.tokens.entry(entry.symbol.to_uppercase()).or_default();
+            for (network, address) in entry.addresses {
+                let network = &networks.get(&network)?.id;
+                let address = address.to_lowercase();
+                if !is_valid_address(&address) {
+                    return Err(anyhow!(
+                        "Invalid {} address '{}' in token map",
+                        entry.symbol,
+                        address
+                    ));
+                }
+                addresses.insert(network.clone(), address);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn networks(list: &[&str]) -> Vec<String> {
+        list.iter().map(|network| network.to_string()).collect()
+    }
+
+    #[test]
+    fn test_tokens_resolve_per_network() {
+        let registry = NetworkRegistry::load(None).unwrap();
+        let map = TokenMap::load(None, &registry).unwrap();
+        let resolve = |token: &str, list: &[&str]| map.resolve(token, &networks(list), &registry);
+
+        let all = resolve("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", &["all"]).unwrap();
+        assert_eq!(all.len(), registry.ids().count());
+
+        let chains = resolve("usdc", &["mainnet", "base", "ethereum"]).unwrap();
+        let resolved: Vec<(&str, &str)> = chains
+            .iter()
+            .map(|chain| (chain.network.id.as_str(), chain.address.as_str()))
+            .collect();
+        assert_eq!(
+            resolved,
+            vec![
+                ("ethereum", "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+                ("base", "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913"),
+            ]
+        );
+
+        // USDT has no bundled Base address, so `all` skips it but naming it fails
+        assert_eq!(resolve("USDT", &["all"]).unwrap().len(), 4);
+        assert!(resolve("USDT", &["base"]).is_err());
+        assert!(resolve("PEPE", &["all"]).is_err());
+    }
+}
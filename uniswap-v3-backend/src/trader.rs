@@ -0,0 +1,328 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+use crate::labels::LabelRegistry;
+use crate::types::{AggregationOptions, Pool, Swap, TraderStats};
+use crate::utils::{
+    aggregate_trader_stats, chronological_key, determine_trade_type, format_signed_usd,
+    format_timestamp, pair_label, pool_label,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+/// One of a trader's swaps, seen from the target token's side.
+#[derive(Debug, Clone, Serialize)]
+pub struct TraderSwap {
+    pub id: String,
+    pub block_number: u64,
+    pub timestamp: u64,
+    pub pool: String,
+    /// Pool tokens as `TOKEN0/TOKEN1`
+    pub pair: String,
+    pub side: TradeSide,
+    pub amount_token: Decimal,
+    pub amount_usd: Decimal,
+}
+
+/// A trader's activity in a single pool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolBreakdown {
+    pub pool: String,
+    pub pair: String,
+    /// Hundredths of a basis point; `None` when the source did not report it
+    pub fee_tier: Option<u32>,
+    pub total_buys: u32,
+    pub total_sells: u32,
+    pub buy_volume_usd: Decimal,
+    pub sell_volume_usd: Decimal,
+    pub total_volume_usd: Decimal,
+}
+
+impl PoolBreakdown {
+    fn new(pool: &Pool) -> Self {
+        Self {
+            pool: pool.id.to_lowercase(),
+            pair: pair_label(pool),
+            fee_tier: pool.fee(),
+            total_buys: 0,
+            total_sells: 0,
+            buy_volume_usd: Decimal::ZERO,
+            sell_volume_usd: Decimal::ZERO,
+            total_volume_usd: Decimal::ZERO,
+        }
+    }
+
+    /// Pair and fee tier, e.g. `USDC/WETH 0.05%`.
+    pub fn label(&self) -> String {
+        pool_label(&self.pair, self.fee_tier)
+    }
+}
+
+/// Adds one of a trader's swaps to their per-pool breakdown.
+pub fn record_pool_swap(
+    pools: &mut Vec<PoolBreakdown>,
+    pool: &Pool,
+    is_buy: bool,
+    amount_usd: Decimal,
+) {
+    let index = match pools
+        .iter()
+        .position(|entry| entry.pool.eq_ignore_ascii_case(&pool.id))
+    {
+        Some(index) => index,
+        None => {
+            pools.push(PoolBreakdown::new(pool));
+            pools.len() - 1
+        }
+    };
+    let entry = &mut pools[index];
+    if is_buy {
+        entry.total_buys += 1;
+        entry.buy_volume_usd += amount_usd;
+    } else {
+        entry.total_sells += 1;
+        entry.sell_volume_usd += amount_usd;
+    }
+    entry.total_volume_usd += amount_usd;
+}
+
+/// Largest USD volume first, ties broken by pool address.
+pub fn sort_pools(pools: &mut [PoolBreakdown]) {
+    pools.sort_by(|a, b| {
+        b.total_volume_usd
+            .cmp(&a.total_volume_usd)
+            .then_with(|| a.pool.cmp(&b.pool))
+    });
+}
+
+/// Everything known about one trader of a token within a window.
+#[derive(Debug, Clone)]
+pub struct TraderHistory {
+    pub stats: TraderStats,
+    /// Newest first
+    pub swaps: Vec<TraderSwap>,
+    /// Largest USD volume first
+    pub pools: Vec<PoolBreakdown>,
+}
+
+/// Builds `trader`'s history from `swaps`, crediting swaps the same way the
+/// leaderboard does. Returns `None` when no swap is credited to the trader.
+pub fn trader_history(
+    swaps: &[Swap],
+    target_token: &str,
+    trader: &str,
+    options: &AggregationOptions,
+) -> Result<Option<TraderHistory>> {
+    let trader = trader.to_lowercase();
+
+    // Stats come from the full set so PnL is marked at the token's latest
+    // price rather than the trader's own last trade
+    let Some(stats) = aggregate_trader_stats(swaps, target_token, options)?.remove(&trader) else {
+        return Ok(None);
+    };
+
+    let mut credited: Vec<&Swap> = swaps
+        .iter()
+        .filter(|swap| {
+            options
+                .trader_address(swap)
+                .is_some_and(|address| address.eq_ignore_ascii_case(&trader))
+        })
+        .collect();
+    credited.sort_by_cached_key(|swap| std::cmp::Reverse(chronological_key(swap)));
+
+    let mut history = Vec::new();
+    let mut pools = Vec::new();
+    for swap in credited {
+        let Ok((is_buy, amount_token, amount_usd)) = determine_trade_type(swap, target_token)
+        else {
+            continue;
+        };
+        record_pool_swap(&mut pools, &swap.pool, is_buy, amount_usd);
+
+        history.push(TraderSwap {
+            id: swap.id.clone(),
+            block_number: swap.transaction.block_number.parse().unwrap_or(0),
+            timestamp: swap.timestamp.parse().unwrap_or(0),
+            pool: swap.pool.id.to_lowercase(),
+            pair: pair_label(&swap.pool),
+            side: if is_buy {
+                TradeSide::Buy
+            } else {
+                TradeSide::Sell
+            },
+            amount_token,
+            amount_usd,
+        });
+    }
+    sort_pools(&mut pools);
+
+    Ok(Some(TraderHistory {
+        stats,
+        swaps: history,
+        pools,
+    }))
+}
+
+/// Writes a trader's stats, pool breakdown and the `limit` swaps after
+/// `offset` as fixed-width tables.
+pub fn write_trader_table(
+    out: &mut dyn Write,
+    history: &TraderHistory,
+    offset: usize,
+    limit: usize,
+    labels: &LabelRegistry,
+) -> io::Result<()> {
+    let stats = &history.stats;
+    writeln!(out, "\n👤 TRADER {}", stats.address)?;
+    writeln!(out, "─────────────────────────────────────────────────────")?;
+    if let Some(label) = labels.get(&stats.address) {
+        writeln!(out, "Label: {} ({:?})", label.label, label.category)?;
+    }
+    writeln!(
+        out,
+        "Buys: {}  Sells: {}",
+        stats.total_buys, stats.total_sells
+    )?;
+    writeln!(out, "Buy Volume (USD): ${:.2}", stats.total_buy_volume_usd)?;
+    writeln!(
+        out,
+        "Sell Volume (USD): ${:.2}",
+        stats.total_sell_volume_usd
+    )?;
+    writeln!(out, "Total Volume (USD): ${:.2}", stats.total_volume_usd())?;
+    let net_volume = stats.net_volume_token();
+    writeln!(
+        out,
+        "Net Token Volume: {}{:.4}",
+        if net_volume >= Decimal::ZERO { "+" } else { "" },
+        net_volume
+    )?;
+    if let Some(pnl) = &stats.pnl {
+        writeln!(
+            out,
+            "Realized PnL (USD): {}",
+            format_signed_usd(pnl.realized_pnl_usd)
+        )?;
+        writeln!(
+            out,
+            "Unrealized PnL (USD): {}",
+            pnl.unrealized_pnl_usd
+                .map(format_signed_usd)
+                .unwrap_or_else(|| "n/a".to_string())
+        )?;
+    }
+
+    writeln!(out, "\n🏊 POOLS")?;
+    writeln!(
+        out,
+        "═══════════════════════════════════════════════════════════════════════════════"
+    )?;
+    writeln!(
+        out,
+        "{:<42} {:<20} {:<8} {:<8} {:<15}",
+        "Pool", "Pair", "Buys", "Sells", "Volume USD"
+    )?;
+    writeln!(
+        out,
+        "───────────────────────────────────────────────────────────────────────────────"
+    )?;
+    for pool in &history.pools {
+        writeln!(
+            out,
+            "{:<42} {:<20} {:<8} {:<8} ${:<14.2}",
+            pool.pool,
+            pool.label(),
+            pool.total_buys,
+            pool.total_sells,
+            pool.total_volume_usd
+        )?;
+    }
+
+    let page: Vec<&TraderSwap> = history.swaps.iter().skip(offset).take(limit).collect();
+    writeln!(
+        out,
+        "\n🔁 SWAPS ({}-{} of {}, newest first)",
+        (offset + 1).min(history.swaps.len()),
+        offset + page.len(),
+        history.swaps.len()
+    )?;
+    writeln!(
+        out,
+        "═══════════════════════════════════════════════════════════════════════════════"
+    )?;
+    writeln!(
+        out,
+        "{:<23} {:<10} {:<14} {:<5} {:<15} {:<15}",
+        "Time", "Block", "Pair", "Side", "Token Amount", "USD Amount"
+    )?;
+    writeln!(
+        out,
+        "───────────────────────────────────────────────────────────────────────────────"
+    )?;
+    for swap in page {
+        writeln!(
+            out,
+            "{:<23} {:<10} {:<14} {:<5} {:<15.4} ${:<14.2}",
+            format_timestamp(swap.timestamp),
+            swap.block_number,
+            swap.pair,
+            match swap.side {
+                TradeSide::Buy => "BUY",
+                TradeSide::Sell => "SELL",
+            },
+            swap.amount_token,
+            swap.amount_usd
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+
+    #[test]
+    fn test_trader_history_lists_swaps_and_pools() {
+        let swaps: Vec<Swap> =
+            serde_json::from_str(include_str!("../fixtures/swaps.json")).unwrap();
+        let trader = "0x1111111111111111111111111111111111111111";
+
+        let history = trader_history(&swaps, WETH, trader, &AggregationOptions::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (history.stats.total_buys, history.stats.total_sells),
+            (2, 1)
+        );
+
+        let ids: Vec<&str> = history.swaps.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["0x04#2", "0x03#4", "0x01#0"]);
+        assert_eq!(history.swaps[0].side, TradeSide::Sell);
+
+        let pools: Vec<(&str, Decimal)> = history
+            .pools
+            .iter()
+            .map(|p| (p.pair.as_str(), p.total_volume_usd))
+            .collect();
+        assert_eq!(
+            pools,
+            vec![
+                ("USDC/WETH", Decimal::from(10_250)),
+                ("WETH/USDT", Decimal::from(2_510))
+            ]
+        );
+
+        let unknown = trader_history(&swaps, WETH, "0x9999", &AggregationOptions::default());
+        assert!(unknown.unwrap().is_none());
+    }
+}
@@ -1,5 +1,12 @@
+use clap::ValueEnum;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::labels::{AddressCategory, LabelRegistry};
+use crate::pnl::{CostBasisMode, TraderPnl};
+use crate::pools::PoolStats;
+use crate::trader::{PoolBreakdown, TraderSwap};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GraphQLQuery {
@@ -16,6 +23,13 @@ pub struct GraphQLResponse<T> {
 #[derive(Debug, Deserialize)]
 pub struct GraphQLError {
     pub message: String,
+    #[serde(default)]
+    pub extensions: Option<GraphQLErrorExtensions>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GraphQLErrorExtensions {
+    pub code: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -23,36 +37,59 @@ pub struct SwapsResponse {
     pub swaps: Vec<Swap>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+// Mirrors the subgraph selection set; not every field is consumed yet
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Swap {
     pub id: String,
     pub timestamp: String,
     pub sender: String,
     pub recipient: String,
+    /// Externally owned account that sent the transaction (`tx.from`)
+    #[serde(default)]
+    pub origin: Option<String>,
     #[serde(rename = "amount0")]
     pub amount_0: String,
     #[serde(rename = "amount1")]
     pub amount_1: String,
     #[serde(rename = "amountUSD")]
     pub amount_usd: String,
+    /// Position of the swap's log within its block
+    #[serde(rename = "logIndex", default)]
+    pub log_index: Option<String>,
     pub pool: Pool,
     pub transaction: Transaction,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Pool {
     pub id: String,
     #[serde(rename = "token0")]
     pub token_0: Token,
     #[serde(rename = "token1")]
     pub token_1: Token,
+    /// Swap fee in hundredths of a basis point, e.g. `500` for 0.05%
+    #[serde(rename = "feeTier", default)]
+    pub fee_tier: Option<String>,
     #[serde(rename = "tick")]
     pub tick: Option<String>,
     #[serde(rename = "sqrtPrice")]
     pub sqrt_price: String,
+    /// Current TVL; only the subgraph reports it
+    #[serde(rename = "totalValueLockedUSD", default)]
+    pub total_value_locked_usd: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl Pool {
+    /// Parsed `fee_tier`; `None` for swaps recorded without one.
+    pub fn fee(&self) -> Option<u32> {
+        self.fee_tier.as_deref()?.parse().ok()
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Token {
     pub id: String,
     pub symbol: String,
@@ -60,12 +97,195 @@ pub struct Token {
     pub decimals: String,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Transaction {
     #[serde(rename = "blockNumber")]
     pub block_number: String,
 }
 
+/// Inclusive block and timestamp bounds applied to a swap query. Any
+/// combination of bounds may be set; unset bounds leave that side open.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SwapWindow {
+    pub start_block: Option<u64>,
+    pub end_block: Option<u64>,
+    pub from_timestamp: Option<u64>,
+    pub to_timestamp: Option<u64>,
+}
+
+impl SwapWindow {
+    pub fn has_time_bounds(&self) -> bool {
+        self.from_timestamp.is_some() || self.to_timestamp.is_some()
+    }
+}
+
+/// Swaps queried only for the block they were included in.
+#[derive(Debug, Deserialize)]
+pub struct SwapBlocksResponse {
+    pub swaps: Vec<SwapBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SwapBlock {
+    pub transaction: Transaction,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MetaResponse {
+    #[serde(rename = "_meta")]
+    pub meta: Meta,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Meta {
+    pub block: MetaBlock,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MetaBlock {
+    pub number: u64,
+}
+
+/// Which address a swap is credited to on the leaderboard.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AttributionMode {
+    /// The EOA that sent the transaction (`tx.from`)
+    #[default]
+    Origin,
+    /// The address that received the swap output
+    Recipient,
+    /// The address that called the pool, usually a router contract
+    Sender,
+}
+
+impl AttributionMode {
+    pub fn address(self, swap: &Swap) -> Option<&str> {
+        let address = match self {
+            Self::Origin => swap.origin.as_deref()?,
+            Self::Recipient => swap.recipient.as_str(),
+            Self::Sender => swap.sender.as_str(),
+        };
+        (!address.is_empty()).then_some(address)
+    }
+}
+
+/// Options controlling how swaps are turned into per-trader statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggregationOptions {
+    pub attribution: AttributionMode,
+    /// Used when the `attribution` address is unavailable
+    pub attribution_fallback: AttributionMode,
+    /// Track per-trader PnL with this cost basis; `None` skips PnL entirely
+    pub cost_basis: Option<CostBasisMode>,
+    /// Split each trader's activity by pool in `TraderStats::pools`
+    pub pool_breakdown: bool,
+}
+
+impl AggregationOptions {
+    /// Address `swap` is credited to, if either attribution mode yields one.
+    pub fn trader_address<'a>(&self, swap: &'a Swap) -> Option<&'a str> {
+        self.attribution
+            .address(swap)
+            .or_else(|| self.attribution_fallback.address(swap))
+    }
+}
+
+impl Default for AggregationOptions {
+    fn default() -> Self {
+        Self {
+            attribution: AttributionMode::Origin,
+            attribution_fallback: AttributionMode::Recipient,
+            cost_basis: None,
+            pool_breakdown: false,
+        }
+    }
+}
+
+/// Restricts a swap fetch to some of a token's pools. An empty list matches
+/// every pool; swaps recorded without a fee tier never match `fee_tiers`.
+#[derive(Debug, Clone, Default)]
+pub struct PoolFilter {
+    /// Lowercase pool addresses
+    pub pools: Vec<String>,
+    /// Fee tiers in hundredths of a basis point, e.g. `3000` for 0.3%
+    pub fee_tiers: Vec<u32>,
+}
+
+impl PoolFilter {
+    pub fn new(pools: &[String], fee_tiers: &[u32]) -> Self {
+        Self {
+            pools: pools
+                .iter()
+                .map(|pool| pool.trim().to_lowercase())
+                .collect(),
+            fee_tiers: fee_tiers.to_vec(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty() && self.fee_tiers.is_empty()
+    }
+
+    pub fn matches(&self, pool: &Pool) -> bool {
+        self.matches_pool(&pool.id, pool.fee())
+    }
+
+    /// Whether a pool with this address and fee tier is selected.
+    pub fn matches_pool(&self, address: &str, fee_tier: Option<u32>) -> bool {
+        (self.pools.is_empty() || self.pools.contains(&address.to_lowercase()))
+            && (self.fee_tiers.is_empty()
+                || fee_tier.is_some_and(|fee| self.fee_tiers.contains(&fee)))
+    }
+}
+
+/// Metric the leaderboard is ranked by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum SortKey {
+    /// Total buy + sell volume in USD
+    #[default]
+    Volume,
+    /// Buy volume in USD
+    BuyVolume,
+    /// Sell volume in USD
+    SellVolume,
+    /// Tokens bought minus tokens sold
+    NetFlow,
+    /// Number of buys plus sells
+    Trades,
+    /// Buys per sell; traders that only buy rank highest
+    BuySellRatio,
+    /// Realized plus unrealized PnL in USD
+    Pnl,
+}
+
+/// Metric the pool leaderboard is ranked by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum PoolSortKey {
+    /// Total buy + sell volume in USD
+    #[default]
+    Volume,
+    /// Number of swaps
+    Swaps,
+    /// Number of distinct traders
+    Traders,
+    /// Total value locked in USD; pools without it rank last
+    Tvl,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Desc,
+    Asc,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TraderStats {
     pub address: String,
@@ -75,6 +295,22 @@ pub struct TraderStats {
     pub total_sell_volume_token: Decimal,
     pub total_buy_volume_usd: Decimal,
     pub total_sell_volume_usd: Decimal,
+    pub pnl: Option<TraderPnl>,
+    /// Activity per network id; only filled in on multi-network leaderboards
+    #[serde(default)]
+    pub networks: BTreeMap<String, NetworkActivity>,
+    /// Activity per pool, largest volume first; only filled in when a pool
+    /// breakdown is requested
+    #[serde(default)]
+    pub pools: Vec<PoolBreakdown>,
+}
+
+/// A trader's share of a multi-network leaderboard on one network.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetworkActivity {
+    pub total_buys: u32,
+    pub total_sells: u32,
+    pub total_volume_usd: Decimal,
 }
 
 impl TraderStats {
@@ -87,6 +323,9 @@ impl TraderStats {
             total_sell_volume_token: Decimal::ZERO,
             total_buy_volume_usd: Decimal::ZERO,
             total_sell_volume_usd: Decimal::ZERO,
+            pnl: None,
+            networks: BTreeMap::new(),
+            pools: Vec::new(),
         }
     }
 
@@ -97,28 +336,180 @@ impl TraderStats {
     pub fn net_volume_token(&self) -> Decimal {
         self.total_buy_volume_token - self.total_sell_volume_token
     }
+
+    pub fn total_trades(&self) -> u32 {
+        self.total_buys + self.total_sells
+    }
+
+    /// Buys per sell; infinite when the trader only bought.
+    pub fn buy_sell_ratio(&self) -> f64 {
+        if self.total_sells > 0 {
+            self.total_buys as f64 / self.total_sells as f64
+        } else if self.total_buys > 0 {
+            f64::INFINITY
+        } else {
+            0.0
+        }
+    }
+
+    pub fn total_pnl_usd(&self) -> Option<Decimal> {
+        self.pnl.as_ref().map(TraderPnl::total_pnl_usd)
+    }
 }
 
 // API types for HTTP server
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LeaderboardRequest {
     pub token_address: Option<String>,
     pub start_block: Option<u64>,
     pub end_block: Option<u64>,
+    /// Unix timestamp (seconds) of the earliest swap to include
+    pub from_timestamp: Option<u64>,
+    /// Unix timestamp (seconds) of the latest swap to include
+    pub to_timestamp: Option<u64>,
+    /// Traders per page (defaults to 20)
     pub limit: Option<usize>,
+    /// Ranked traders to skip before the page starts
+    pub offset: Option<usize>,
+    /// `next_cursor` from a previous page; resumes after that page's last
+    /// trader even if ranks above it have shifted. Exclusive with `offset`.
+    pub cursor: Option<String>,
+    /// Address each swap is credited to (defaults to `origin`)
+    pub attribution: Option<AttributionMode>,
+    /// Used when the attribution address is unavailable (defaults to `recipient`)
+    pub attribution_fallback: Option<AttributionMode>,
+    /// Cost basis used for PnL; PnL is only computed when set or ranking by PnL
+    pub pnl_mode: Option<CostBasisMode>,
+    pub sort_by: Option<SortKey>,
+    pub order: Option<SortOrder>,
+    /// Labeled address categories left out of the ranking
+    pub exclude_categories: Option<Vec<AddressCategory>>,
     pub demo: Option<bool>,
     pub network: Option<String>,
+    /// Networks to combine into one leaderboard, or `["all"]`; overrides
+    /// `network` and resolves `token_address` through the token map
+    pub networks: Option<Vec<String>>,
+    /// Only count swaps in these pool addresses
+    pub pools: Option<Vec<String>>,
+    /// Only count swaps in pools with these fee tiers (e.g. `500` for 0.05%)
+    pub fee_tiers: Option<Vec<u32>>,
+    /// Include each trader's activity split by pool
+    pub pool_breakdown: Option<bool>,
+}
+
+/// Query string of `GET /api/v1/{network}/tokens/{address}/leaderboard`.
+/// Network and token come from the path; everything else mirrors
+/// `LeaderboardRequest`.
+#[derive(Debug, Default, Deserialize)]
+pub struct LeaderboardQuery {
+    /// Start of the time window, in any form `--since` accepts
+    pub from: Option<String>,
+    /// End of the time window, in any form `--until` accepts
+    pub to: Option<String>,
+    pub start_block: Option<u64>,
+    pub end_block: Option<u64>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    pub cursor: Option<String>,
+    pub attribution: Option<AttributionMode>,
+    pub attribution_fallback: Option<AttributionMode>,
+    pub pnl_mode: Option<CostBasisMode>,
+    pub sort_by: Option<SortKey>,
+    pub order: Option<SortOrder>,
+    /// Comma-separated address categories, e.g. `router,mev_bot`
+    pub exclude: Option<String>,
+    pub demo: Option<bool>,
+    /// Comma-separated pool addresses
+    pub pools: Option<String>,
+    /// Comma-separated fee tiers, e.g. `500,3000`
+    pub fee_tiers: Option<String>,
+    pub pool_breakdown: Option<bool>,
+}
+
+/// Query string of `GET /api/v1/{network}/tokens/{token}/traders/{address}`.
+#[derive(Debug, Default, Deserialize)]
+pub struct TraderQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub start_block: Option<u64>,
+    pub end_block: Option<u64>,
+    pub attribution: Option<AttributionMode>,
+    pub attribution_fallback: Option<AttributionMode>,
+    pub pnl_mode: Option<CostBasisMode>,
+    /// Swaps to skip, newest first
+    pub offset: Option<usize>,
+    /// Swaps per page (defaults to 50, at most 500)
+    pub limit: Option<usize>,
+}
+
+/// Query string of `GET /api/v1/{network}/tokens/{token}/pools`.
+#[derive(Debug, Default, Deserialize)]
+pub struct PoolsQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub start_block: Option<u64>,
+    pub end_block: Option<u64>,
+    /// Decides who counts as a pool's trader, as on the leaderboard
+    pub attribution: Option<AttributionMode>,
+    pub attribution_fallback: Option<AttributionMode>,
+    pub sort_by: Option<PoolSortKey>,
+    pub order: Option<SortOrder>,
+    /// Pools to return (defaults to 20)
+    pub limit: Option<usize>,
+    /// Comma-separated pool addresses
+    pub pools: Option<String>,
+    /// Comma-separated fee tiers, e.g. `500,3000`
+    pub fee_tiers: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TraderDetailResponse {
+    pub trader: TraderStatsAPI,
+    pub pools: Vec<PoolBreakdown>,
+    /// One page of the trader's swaps, newest first
+    pub swaps: Vec<TraderSwap>,
+    pub total_swaps: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub window: SwapWindow,
+}
+
+/// One page of a leaderboard. `summary` always covers every ranked trader,
+/// not just the page.
+#[derive(Debug, Serialize)]
+pub struct LeaderboardResponse<'a> {
+    pub traders: &'a [TraderStatsAPI],
+    pub summary: &'a SummaryStats,
+    pub page: PageInfo,
+    pub window: &'a SwapWindow,
 }
 
+/// The top pools for a token. `total_pools` counts every pool that had a
+/// swap in the window.
 #[derive(Debug, Serialize)]
-pub struct LeaderboardResponse {
-    pub traders: Vec<TraderStatsAPI>,
-    pub summary: SummaryStats,
+pub struct PoolLeaderboardResponse<'a> {
+    pub pools: &'a [PoolStats],
+    pub total_pools: usize,
+    pub window: &'a SwapWindow,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PageInfo {
+    pub offset: usize,
+    pub limit: usize,
+    /// Offset of the next page, `None` on the last page
+    pub next_offset: Option<usize>,
+    /// Opaque cursor for the next page, `None` on the last page
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct TraderStatsAPI {
+    /// 1-based position on the full leaderboard; `None` outside a leaderboard
+    pub rank: Option<usize>,
     pub address: String,
+    pub label: Option<String>,
+    pub category: Option<AddressCategory>,
     pub total_buys: u32,
     pub total_sells: u32,
     pub total_buy_volume_token: String,
@@ -127,7 +518,58 @@ pub struct TraderStatsAPI {
     pub total_sell_volume_usd: String,
     pub total_volume_usd: String,
     pub net_volume_token: String,
-    pub buy_sell_ratio: f64,
+    /// Buys per sell, as ranked by `buy_sell_ratio`; `None` without sells
+    pub buy_sell_ratio: Option<f64>,
+    pub realized_pnl_usd: Option<String>,
+    pub unrealized_pnl_usd: Option<String>,
+    pub position_token: Option<String>,
+    pub average_entry_price_usd: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub networks: BTreeMap<String, NetworkActivity>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pools: Vec<PoolBreakdown>,
+}
+
+impl TraderStatsAPI {
+    /// API view of `stats`, with its label if the address has one. `rank` is
+    /// left unset.
+    pub fn from_stats(stats: TraderStats, labels: &LabelRegistry) -> Self {
+        let pnl = stats.pnl.as_ref();
+        let label = labels.get(&stats.address).cloned();
+        let total_volume = stats.total_buy_volume_usd + stats.total_sell_volume_usd;
+        let net_volume = stats.total_buy_volume_token - stats.total_sell_volume_token;
+        let buy_sell_ratio = (stats.total_sells > 0).then(|| stats.buy_sell_ratio());
+
+        Self {
+            rank: None,
+            address: stats.address,
+            label: label.as_ref().map(|label| label.label.clone()),
+            category: label.map(|label| label.category),
+            total_buys: stats.total_buys,
+            total_sells: stats.total_sells,
+            total_buy_volume_token: stats.total_buy_volume_token.to_string(),
+            total_sell_volume_token: stats.total_sell_volume_token.to_string(),
+            total_buy_volume_usd: stats.total_buy_volume_usd.to_string(),
+            total_sell_volume_usd: stats.total_sell_volume_usd.to_string(),
+            total_volume_usd: total_volume.to_string(),
+            net_volume_token: if net_volume >= Decimal::ZERO {
+                format!("+{}", net_volume)
+            } else {
+                net_volume.to_string()
+            },
+            buy_sell_ratio,
+            realized_pnl_usd: pnl.map(|pnl| pnl.realized_pnl_usd.to_string()),
+            unrealized_pnl_usd: pnl
+                .and_then(|pnl| pnl.unrealized_pnl_usd)
+                .map(|value| value.to_string()),
+            position_token: pnl.map(|pnl| pnl.position_token.to_string()),
+            average_entry_price_usd: pnl
+                .and_then(|pnl| pnl.average_entry_price_usd)
+                .map(|value| value.to_string()),
+            networks: stats.networks,
+            pools: stats.pools,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -138,3 +580,22 @@ pub struct SummaryStats {
     pub total_sell_transactions: u32,
     pub average_volume_per_trader: String,
 }
+
+impl SummaryStats {
+    /// Totals over every trader in `traders`.
+    pub fn from_traders(traders: &[TraderStats]) -> Self {
+        let total_traders = traders.len();
+        let total_volume: Decimal = traders.iter().map(|t| t.total_volume_usd()).sum();
+        Self {
+            total_traders,
+            total_volume_usd: format!("{:.2}", total_volume),
+            total_buy_transactions: traders.iter().map(|t| t.total_buys).sum(),
+            total_sell_transactions: traders.iter().map(|t| t.total_sells).sum(),
+            average_volume_per_trader: if total_traders > 0 {
+                format!("{:.2}", total_volume / Decimal::from(total_traders))
+            } else {
+                "0.00".to_string()
+            },
+        }
+    }
+}
@@ -1,8 +1,35 @@
 use anyhow::{Result, anyhow};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use rust_decimal::Decimal;
-use std::collections::HashMap;
+use rust_decimal::prelude::FromPrimitive;
+use serde::de::DeserializeOwned;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, Write};
+use std::path::Path;
 
-use crate::types::{Swap, TraderStats};
+use crate::labels::LabelRegistry;
+use crate::pnl::PositionTracker;
+use crate::trader::{record_pool_swap, sort_pools};
+use crate::types::{AggregationOptions, Pool, SortKey, SortOrder, SummaryStats, Swap, TraderStats};
+
+/// Reads a user registry file (labels, tokens or networks): TOML when the
+/// file has a `.toml` extension, JSON otherwise. `kind` names it in errors.
+pub fn load_registry_file<T: DeserializeOwned>(path: &Path, kind: &str) -> Result<T> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read {} {}: {}", kind, path.display(), e))?;
+    let source = path.display().to_string();
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => parse_toml(&contents, &source),
+        _ => serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("Failed to parse {} {}: {}", kind, source, e)),
+    }
+}
+
+pub fn parse_toml<T: DeserializeOwned>(contents: &str, source: &str) -> Result<T> {
+    toml::from_str(contents).map_err(|e| anyhow!("Failed to parse {}: {}", source, e))
+}
 
 pub fn parse_decimal(s: &str) -> Result<Decimal> {
     s.parse::<Decimal>()
@@ -37,23 +64,45 @@ pub fn determine_trade_type(swap: &Swap, target_token: &str) -> Result<(bool, De
     }
 }
 
+/// Aggregates per-trader statistics, crediting each swap to the address chosen
+/// by `options.attribution` (or its fallback). Swaps are replayed oldest first
+/// so that PnL, when enabled, sees each trader's buys and sells in order.
 pub fn aggregate_trader_stats(
     swaps: &[Swap],
     target_token: &str,
+    options: &AggregationOptions,
 ) -> Result<HashMap<String, TraderStats>> {
     let mut trader_stats: HashMap<String, TraderStats> = HashMap::new();
+    let mut positions: HashMap<String, PositionTracker> = HashMap::new();
+
+    eprintln!("Processing {} swaps...", swaps.len());
 
-    println!("Processing {} swaps...", swaps.len());
+    let mut ordered: Vec<&Swap> = swaps.iter().collect();
+    ordered.sort_by_cached_key(|swap| chronological_key(swap));
 
-    for (i, swap) in swaps.iter().enumerate() {
+    for (i, swap) in ordered.iter().enumerate() {
         if i % 1000 == 0 && i > 0 {
-            println!("Processed {} swaps", i);
+            eprintln!("Processed {} swaps", i);
         }
 
         match determine_trade_type(swap, target_token) {
             Ok((is_buy, token_amount, usd_amount)) => {
-                // Use sender as the trader address (the one initiating the swap)
-                let trader_address = swap.sender.clone();
+                let Some(trader_address) = options.trader_address(swap) else {
+                    eprintln!("Warning: No trader address for swap {}", swap.id);
+                    continue;
+                };
+                let trader_address = trader_address.to_lowercase();
+
+                if let Some(mode) = options.cost_basis {
+                    let position = positions
+                        .entry(trader_address.clone())
+                        .or_insert_with(|| PositionTracker::new(mode));
+                    if is_buy {
+                        position.buy(token_amount, usd_amount);
+                    } else {
+                        position.sell(token_amount, usd_amount);
+                    }
+                }
 
                 let stats = trader_stats
                     .entry(trader_address.clone())
@@ -68,6 +117,9 @@ pub fn aggregate_trader_stats(
                     stats.total_sell_volume_token += token_amount;
                     stats.total_sell_volume_usd += usd_amount;
                 }
+                if options.pool_breakdown {
+                    record_pool_swap(&mut stats.pools, &swap.pool, is_buy, usd_amount);
+                }
             }
             Err(e) => {
                 eprintln!("Warning: Failed to process swap {}: {}", swap.id, e);
@@ -76,47 +128,213 @@ pub fn aggregate_trader_stats(
         }
     }
 
-    println!(
+    if options.cost_basis.is_some() {
+        let mark_price = mark_price_usd(&ordered, target_token);
+        match mark_price {
+            Some(price) => eprintln!("Marking open positions at ${:.4}", price),
+            None => eprintln!("⚠️  Could not derive a mark price; unrealized PnL is unavailable"),
+        }
+        for (address, position) in &positions {
+            if let Some(stats) = trader_stats.get_mut(address) {
+                stats.pnl = Some(position.pnl(mark_price));
+            }
+        }
+    }
+
+    for stats in trader_stats.values_mut() {
+        sort_pools(&mut stats.pools);
+    }
+
+    eprintln!(
         "Processed all swaps. Found {} unique traders.",
         trader_stats.len()
     );
     Ok(trader_stats)
 }
 
-pub fn print_leaderboard(trader_stats: HashMap<String, TraderStats>, limit: usize) {
+/// Oldest-first execution order: block, then the swap's log index within the
+/// block, which also orders transactions. Swaps without a `logIndex` fall back
+/// to the number after `#` in their id; the id itself only breaks exact ties.
+pub fn chronological_key(swap: &Swap) -> (u64, u64, String) {
+    let log_index = swap
+        .log_index
+        .as_deref()
+        .or_else(|| swap.id.split_once('#').map(|(_, index)| index))
+        .and_then(|index| index.parse().ok())
+        .unwrap_or(0);
+    (
+        swap.transaction.block_number.parse().unwrap_or(0),
+        log_index,
+        swap.id.clone(),
+    )
+}
+
+/// Price of token0 in units of token1, derived from the pool's `sqrtPriceX96`
+/// (or its tick when no square-root price is available) and both tokens'
+/// decimals.
+pub fn pool_price(pool: &Pool) -> Option<f64> {
+    let decimals_0: i32 = pool.token_0.decimals.parse().ok()?;
+    let decimals_1: i32 = pool.token_1.decimals.parse().ok()?;
+
+    let raw_price = match pool.sqrt_price.parse::<f64>().ok().filter(|p| *p > 0.0) {
+        Some(sqrt_price) => {
+            let ratio = sqrt_price / 2f64.powi(96);
+            ratio * ratio
+        }
+        None => 1.0001f64.powi(pool.tick.as_deref()?.parse().ok()?),
+    };
+    let price = raw_price * 10f64.powi(decimals_0 - decimals_1);
+    (price.is_finite() && price > 0.0).then_some(price)
+}
+
+/// USD price of the target token at the most recent priceable swap: the pool
+/// price from `sqrtPrice`, converted to USD using the quote token's USD value
+/// implied by that swap's `amountUSD`.
+fn mark_price_usd(chronological: &[&Swap], target_token: &str) -> Option<Decimal> {
+    let target_token = target_token.to_lowercase();
+
+    chronological.iter().rev().find_map(|swap| {
+        let price = pool_price(&swap.pool)?;
+        let (price_in_quote, quote_amount) = if swap.pool.token_0.id.to_lowercase() == target_token
+        {
+            (price, &swap.amount_1)
+        } else if swap.pool.token_1.id.to_lowercase() == target_token {
+            (1.0 / price, &swap.amount_0)
+        } else {
+            return None;
+        };
+
+        let quote_amount = parse_decimal(quote_amount).ok()?.abs();
+        let amount_usd = parse_decimal(&swap.amount_usd).ok()?;
+        if quote_amount.is_zero() || amount_usd.is_zero() {
+            return None;
+        }
+
+        Some(Decimal::from_f64(price_in_quote)? * amount_usd / quote_amount)
+    })
+}
+
+/// Orders traders for display by `sort_by` in the given direction. Ties are
+/// broken by ascending address so rankings are reproducible, and when ranking
+/// by PnL, traders without PnL always come last.
+pub fn rank_traders(
+    trader_stats: HashMap<String, TraderStats>,
+    sort_by: SortKey,
+    order: SortOrder,
+) -> Vec<TraderStats> {
     let mut traders: Vec<TraderStats> = trader_stats.into_values().collect();
+    let missing_metric = |t: &TraderStats| sort_by == SortKey::Pnl && t.pnl.is_none();
+
+    traders.sort_by(|a, b| {
+        missing_metric(a)
+            .cmp(&missing_metric(b))
+            .then_with(|| {
+                let ordering = compare_metric(a, b, sort_by);
+                match order {
+                    SortOrder::Asc => ordering,
+                    SortOrder::Desc => ordering.reverse(),
+                }
+            })
+            .then_with(|| a.address.cmp(&b.address))
+    });
+    traders
+}
+
+fn compare_metric(a: &TraderStats, b: &TraderStats, sort_by: SortKey) -> Ordering {
+    match sort_by {
+        SortKey::Volume => a.total_volume_usd().cmp(&b.total_volume_usd()),
+        SortKey::BuyVolume => a.total_buy_volume_usd.cmp(&b.total_buy_volume_usd),
+        SortKey::SellVolume => a.total_sell_volume_usd.cmp(&b.total_sell_volume_usd),
+        SortKey::NetFlow => a.net_volume_token().cmp(&b.net_volume_token()),
+        SortKey::Trades => a.total_trades().cmp(&b.total_trades()),
+        SortKey::BuySellRatio => a.buy_sell_ratio().total_cmp(&b.buy_sell_ratio()),
+        SortKey::Pnl => a.total_pnl_usd().cmp(&b.total_pnl_usd()),
+    }
+}
+
+/// Fee tier in hundredths of a basis point as a percentage, e.g. `500` as `0.05%`.
+pub fn format_fee_tier(fee_tier: u32) -> String {
+    format!("{}%", Decimal::new(fee_tier.into(), 4).normalize())
+}
+
+/// Whether `address` is `0x` followed by 40 hex digits, in either case.
+pub fn is_valid_address(address: &str) -> bool {
+    address.len() == 42
+        && address.starts_with("0x")
+        && address[2..].chars().all(|c| c.is_ascii_hexdigit())
+}
 
-    // Sort by total USD volume (descending)
-    traders.sort_by(|a, b| b.total_volume_usd().cmp(&a.total_volume_usd()));
+/// Pool tokens as `TOKEN0/TOKEN1`, e.g. `USDC/WETH`.
+pub fn pair_label(pool: &Pool) -> String {
+    format!("{}/{}", pool.token_0.symbol, pool.token_1.symbol)
+}
 
-    println!("\n🏆 UNISWAP V3 TRADER LEADERBOARD 🏆");
-    println!(
+/// Pool pair and fee tier, e.g. `USDC/WETH 0.05%`, or just the pair when the
+/// fee tier is unknown.
+pub fn pool_label(pair: &str, fee_tier: Option<u32>) -> String {
+    match fee_tier {
+        Some(fee) => format!("{} {}", pair, format_fee_tier(fee)),
+        None => pair.to_string(),
+    }
+}
+
+pub fn format_signed_usd(value: Decimal) -> String {
+    if value >= Decimal::ZERO {
+        format!("+${:.2}", value)
+    } else {
+        format!("-${:.2}", value.abs())
+    }
+}
+
+/// Writes the top `limit` of `traders`, which must already be ranked (see
+/// `rank_traders`), as a fixed-width table. PnL columns are shown when PnL was
+/// computed.
+pub fn write_leaderboard_table(
+    out: &mut dyn Write,
+    traders: &[TraderStats],
+    summary: &SummaryStats,
+    limit: usize,
+    labels: &LabelRegistry,
+) -> io::Result<()> {
+    let show_pnl = traders.iter().any(|t| t.pnl.is_some());
+    let networks = leaderboard_networks(traders);
+
+    writeln!(out, "\n🏆 UNISWAP V3 TRADER LEADERBOARD 🏆")?;
+    writeln!(
+        out,
         "═══════════════════════════════════════════════════════════════════════════════════════"
-    );
-    println!(
-        "{:<4} {:<42} {:<8} {:<8} {:<15} {:<15} {:<15}",
+    )?;
+    let pnl_header = if show_pnl {
+        format!("{:<15} {:<15} ", "Realized PnL", "Unrealized PnL")
+    } else {
+        String::new()
+    };
+    let network_header: String = networks
+        .iter()
+        .map(|network| format!("{:<15} ", format!("{} Vol", network)))
+        .collect();
+    writeln!(
+        out,
+        "{:<4} {:<42} {:<8} {:<8} {:<15} {:<15} {:<15} {}{}Label",
         "Rank",
         "Trader Address",
         "Buys",
         "Sells",
         "Total Vol USD",
         "Net Token Vol",
-        "Buy/Sell Ratio"
-    );
-    println!(
+        "Buy/Sell Ratio",
+        pnl_header,
+        network_header
+    )?;
+    writeln!(
+        out,
         "─────────────────────────────────────────────────────────────────────────────────────────"
-    );
+    )?;
 
     for (i, trader) in traders.iter().take(limit).enumerate() {
-        let buy_sell_ratio = if trader.total_sells > 0 {
-            format!(
-                "{:.2}",
-                trader.total_buys as f64 / trader.total_sells as f64
-            )
-        } else if trader.total_buys > 0 {
-            "∞".to_string()
-        } else {
-            "0".to_string()
+        let buy_sell_ratio = match trader.buy_sell_ratio() {
+            ratio if ratio.is_infinite() => "∞".to_string(),
+            ratio => format!("{:.2}", ratio),
         };
 
         let net_volume = trader.net_volume_token();
@@ -126,50 +344,189 @@ pub fn print_leaderboard(trader_stats: HashMap<String, TraderStats>, limit: usiz
             format!("{:.4}", net_volume)
         };
 
-        println!(
-            "{:<4} {:<42} {:<8} {:<8} ${:<14.2} {:<15} {:<15}",
+        let label = labels
+            .get(&trader.address)
+            .map(|label| label.label.as_str())
+            .unwrap_or("");
+
+        let pnl_columns = match (&trader.pnl, show_pnl) {
+            (Some(pnl), _) => format!(
+                "{:<15} {:<15} ",
+                format_signed_usd(pnl.realized_pnl_usd),
+                pnl.unrealized_pnl_usd
+                    .map(format_signed_usd)
+                    .unwrap_or_else(|| "n/a".to_string())
+            ),
+            (None, true) => format!("{:<15} {:<15} ", "n/a", "n/a"),
+            (None, false) => String::new(),
+        };
+        let network_columns: String = networks
+            .iter()
+            .map(|network| match trader.networks.get(*network) {
+                Some(activity) => format!("${:<14.2} ", activity.total_volume_usd),
+                None => format!("{:<15} ", "-"),
+            })
+            .collect();
+
+        writeln!(
+            out,
+            "{:<4} {:<42} {:<8} {:<8} ${:<14.2} {:<15} {:<15} {}{}{}",
             i + 1,
             trader.address,
             trader.total_buys,
             trader.total_sells,
             trader.total_volume_usd(),
             net_volume_str,
-            buy_sell_ratio
-        );
+            buy_sell_ratio,
+            pnl_columns,
+            network_columns,
+            label
+        )?;
+        for pool in &trader.pools {
+            writeln!(
+                out,
+                "{:<4} {:<42} {:<8} {:<8} ${:<14.2} {}",
+                "  ↳",
+                pool.pool,
+                pool.total_buys,
+                pool.total_sells,
+                pool.total_volume_usd,
+                pool.label()
+            )?;
+        }
     }
 
-    println!(
+    writeln!(
+        out,
         "═══════════════════════════════════════════════════════════════════════════════════════"
-    );
+    )?;
 
-    // Summary statistics
-    let total_traders = traders.len();
-    let total_volume: Decimal = traders.iter().map(|t| t.total_volume_usd()).sum();
-    let total_buys: u32 = traders.iter().map(|t| t.total_buys).sum();
-    let total_sells: u32 = traders.iter().map(|t| t.total_sells).sum();
-
-    println!("\n📊 SUMMARY STATISTICS");
-    println!("─────────────────────");
-    println!("Total Traders: {}", total_traders);
-    println!("Total Volume (USD): ${:.2}", total_volume);
-    println!("Total Buy Transactions: {}", total_buys);
-    println!("Total Sell Transactions: {}", total_sells);
-    println!(
-        "Average Volume per Trader: ${:.2}",
-        if total_traders > 0 {
-            total_volume / Decimal::from(total_traders)
-        } else {
-            Decimal::ZERO
+    writeln!(out, "\n📊 SUMMARY STATISTICS")?;
+    writeln!(out, "─────────────────────")?;
+    writeln!(out, "Total Traders: {}", summary.total_traders)?;
+    writeln!(out, "Total Volume (USD): ${}", summary.total_volume_usd)?;
+    writeln!(
+        out,
+        "Total Buy Transactions: {}",
+        summary.total_buy_transactions
+    )?;
+    writeln!(
+        out,
+        "Total Sell Transactions: {}",
+        summary.total_sell_transactions
+    )?;
+    if show_pnl {
+        let total_realized: Decimal = traders
+            .iter()
+            .filter_map(|t| t.pnl.as_ref())
+            .map(|pnl| pnl.realized_pnl_usd)
+            .sum();
+        writeln!(
+            out,
+            "Total Realized PnL (USD): {}",
+            format_signed_usd(total_realized)
+        )?;
+    }
+    writeln!(
+        out,
+        "Average Volume per Trader: ${}",
+        summary.average_volume_per_trader
+    )?;
+    Ok(())
+}
+
+/// Every network any of `traders` has per-network activity on, in order.
+pub fn leaderboard_networks(traders: &[TraderStats]) -> Vec<&str> {
+    let networks: BTreeSet<&str> = traders
+        .iter()
+        .flat_map(|trader| trader.networks.keys().map(String::as_str))
+        .collect();
+    networks.into_iter().collect()
+}
+
+/// Start block for the default lookback window ending at `latest_block`.
+/// `lookback_blocks` comes from the network's `default_start_block_offset`.
+pub fn default_start_block(latest_block: u64, lookback_blocks: u64) -> u64 {
+    latest_block.saturating_sub(lookback_blocks)
+}
+
+/// Which end of a time window a user-supplied bound describes. A bare date
+/// covers the whole day, so it resolves to midnight for `Start` and to the last
+/// second of the day for `End`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBound {
+    Start,
+    End,
+}
+
+/// Parses a `--since`/`--until` value into a unix timestamp. Accepts durations
+/// counted back from `now` (`30m`, `24h`, `7d`, `2w`), dates (`2024-05-01`),
+/// RFC 3339 datetimes and raw unix timestamps.
+pub fn parse_time_bound(input: &str, bound: TimeBound, now: DateTime<Utc>) -> Result<u64> {
+    let input = input.trim();
+
+    if !input.is_empty() && input.chars().all(|c| c.is_ascii_digit()) {
+        return input
+            .parse()
+            .map_err(|_| anyhow!("Invalid unix timestamp '{}'", input));
+    }
+
+    if let Some(unit) = input.chars().last().filter(|c| c.is_ascii_alphabetic()) {
+        let amount = &input[..input.len() - 1];
+        if !amount.is_empty() && amount.chars().all(|c| c.is_ascii_digit()) {
+            let amount: i64 = amount
+                .parse()
+                .map_err(|_| anyhow!("Invalid duration '{}'", input))?;
+            let duration = match unit {
+                's' => TimeDelta::try_seconds(amount),
+                'm' => TimeDelta::try_minutes(amount),
+                'h' => TimeDelta::try_hours(amount),
+                'd' => TimeDelta::try_days(amount),
+                'w' => TimeDelta::try_weeks(amount),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid duration unit in '{}'. Use s, m, h, d or w",
+                        input
+                    ));
+                }
+            };
+            let time = duration
+                .and_then(|duration| now.checked_sub_signed(duration))
+                .ok_or_else(|| anyhow!("Duration '{}' is too large", input))?;
+            return to_unix(time, input);
         }
-    );
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        let time = match bound {
+            TimeBound::Start => date.and_hms_opt(0, 0, 0),
+            TimeBound::End => date.and_hms_opt(23, 59, 59),
+        }
+        .ok_or_else(|| anyhow!("Invalid date '{}'", input))?;
+        return to_unix(time.and_utc(), input);
+    }
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+        return to_unix(datetime.with_timezone(&Utc), input);
+    }
+
+    Err(anyhow!(
+        "Invalid time '{}'. Expected a date (2024-05-01), an RFC 3339 datetime, a unix timestamp or a relative duration (7d, 24h)",
+        input
+    ))
 }
 
-pub fn get_default_start_block() -> u64 {
-    // Approximate block number for 30 days ago
-    // Ethereum produces ~1 block every 12 seconds
-    // 30 days = 30 * 24 * 60 * 60 / 12 = 216,000 blocks
-    let current_block = 18_500_000u64; // Approximate current block (this would be dynamic in production)
-    current_block.saturating_sub(216_000)
+fn to_unix(time: DateTime<Utc>, input: &str) -> Result<u64> {
+    u64::try_from(time.timestamp()).map_err(|_| anyhow!("Time '{}' is before 1970", input))
+}
+
+pub fn format_timestamp(timestamp: u64) -> String {
+    i64::try_from(timestamp)
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .map_or(timestamp.to_string(), |time| {
+            time.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+        })
 }
 
 pub fn generate_demo_data() -> HashMap<String, TraderStats> {
@@ -265,3 +622,284 @@ pub fn generate_demo_data() -> HashMap<String, TraderStats> {
 
     trader_stats
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pnl::CostBasisMode;
+    use crate::types::{AttributionMode, PoolFilter};
+
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+
+    fn fixture_swaps() -> Vec<Swap> {
+        serde_json::from_str(include_str!("../fixtures/swaps.json")).unwrap()
+    }
+
+    #[test]
+    fn test_aggregate_attributes_to_origin_with_fallback() {
+        let stats =
+            aggregate_trader_stats(&fixture_swaps(), WETH, &AggregationOptions::default()).unwrap();
+
+        assert_eq!(stats.len(), 3);
+        let a = &stats["0x1111111111111111111111111111111111111111"];
+        assert_eq!((a.total_buys, a.total_sells), (2, 1));
+        assert_eq!(a.total_buy_volume_token, Decimal::new(25, 1));
+        // Swap 0x05#0 has no origin, so it falls back to its recipient
+        assert_eq!(
+            stats["0x3333333333333333333333333333333333333333"].total_buys,
+            1
+        );
+    }
+
+    #[test]
+    fn test_aggregate_by_sender_groups_router_trades() {
+        let options = AggregationOptions {
+            attribution: AttributionMode::Sender,
+            attribution_fallback: AttributionMode::Sender,
+            cost_basis: None,
+            pool_breakdown: false,
+        };
+        let stats = aggregate_trader_stats(&fixture_swaps(), WETH, &options).unwrap();
+
+        assert_eq!(stats.len(), 2);
+        let router = &stats["0x68b3465833fb72a70ecdf485e0e4c7bd8665fc45"];
+        assert_eq!(router.total_buys + router.total_sells, 5);
+    }
+
+    #[test]
+    fn test_pool_filter_and_breakdown() {
+        let options = AggregationOptions {
+            pool_breakdown: true,
+            ..Default::default()
+        };
+        let stats = aggregate_trader_stats(&fixture_swaps(), WETH, &options).unwrap();
+        let pools: Vec<(String, Decimal)> = stats["0x1111111111111111111111111111111111111111"]
+            .pools
+            .iter()
+            .map(|pool| (pool.label(), pool.total_volume_usd))
+            .collect();
+        assert_eq!(
+            pools,
+            vec![
+                ("USDC/WETH 0.05%".to_string(), Decimal::from(10_250)),
+                ("WETH/USDT 0.3%".to_string(), Decimal::from(2_510)),
+            ]
+        );
+
+        let swaps = fixture_swaps();
+        let filter = PoolFilter::new(&[], &[3000]);
+        let selected: Vec<Swap> = swaps
+            .iter()
+            .filter(|swap| filter.matches(&swap.pool))
+            .cloned()
+            .collect();
+        assert!(selected.iter().all(|swap| swap.pool.fee() == Some(3000)));
+        let stats = aggregate_trader_stats(&selected, WETH, &options).unwrap();
+        let a = &stats["0x1111111111111111111111111111111111111111"];
+        assert_eq!(a.total_volume_usd(), Decimal::from(2_510));
+        assert_eq!(a.pools.len(), 1);
+
+        let by_id = PoolFilter::new(
+            &["0x88E6A0c2dDD26FEEb64F039a2c41296FcB3f5640".to_string()],
+            &[],
+        );
+        assert_eq!(
+            swaps
+                .iter()
+                .filter(|swap| by_id.matches(&swap.pool))
+                .count(),
+            4
+        );
+        assert_eq!(format_fee_tier(10_000), "1%");
+    }
+
+    #[test]
+    fn test_registry_files_are_parsed_by_extension() {
+        let dir = std::env::temp_dir().join(format!("registry-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (toml_path, json_path) = (dir.join("tiers.toml"), dir.join("tiers.json"));
+        std::fs::write(&toml_path, "low = 500\n").unwrap();
+        std::fs::write(&json_path, r#"{ "low": 500 }"#).unwrap();
+
+        for path in [&toml_path, &json_path] {
+            let tiers: HashMap<String, u32> = load_registry_file(path, "tier file").unwrap();
+            assert_eq!(tiers["low"], 500);
+        }
+        let missing =
+            load_registry_file::<HashMap<String, u32>>(&dir.join("none.json"), "tier file");
+        assert!(
+            missing
+                .unwrap_err()
+                .to_string()
+                .contains("Failed to read tier file")
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_is_valid_address() {
+        assert!(is_valid_address(WETH));
+        assert!(is_valid_address(
+            "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        ));
+        assert!(!is_valid_address(&WETH[2..]));
+        assert!(!is_valid_address(&WETH[..41]));
+        assert!(!is_valid_address(
+            "0xz02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"
+        ));
+    }
+
+    #[test]
+    fn test_chronological_key_follows_block_and_log_index() {
+        let swaps = fixture_swaps();
+        let key = |id: &str| chronological_key(swaps.iter().find(|s| s.id == id).unwrap());
+        // Same block: log 0 runs before log 3 whatever the tx hashes
+        assert!(key("0x07#0") < key("0x06#3"));
+        assert!(key("0x05#0") < key("0x06#3"));
+
+        let mut early = swaps[0].clone();
+        early.id = "0xff#0".to_string();
+        early.log_index = Some("2".to_string());
+        let mut late = early.clone();
+        late.id = "0xaa#0".to_string();
+        late.log_index = Some("7".to_string());
+        assert!(chronological_key(&early) < chronological_key(&late));
+    }
+
+    #[test]
+    fn test_fifo_pnl_marks_open_positions_at_latest_pool_price() {
+        let options = AggregationOptions {
+            cost_basis: Some(CostBasisMode::Fifo),
+            ..Default::default()
+        };
+        let stats = aggregate_trader_stats(&fixture_swaps(), WETH, &options).unwrap();
+
+        // A bought 1.5 @ 2500 and 1 @ 2510, then sold all 2.5 @ 2600
+        let a = stats["0x1111111111111111111111111111111111111111"]
+            .pnl
+            .as_ref()
+            .unwrap();
+        assert_eq!(a.realized_pnl_usd, Decimal::from(240));
+        assert_eq!(a.position_token, Decimal::ZERO);
+
+        // C holds 0.5 bought @ 2600, marked at the last USDC/WETH price of 2650
+        let c = stats["0x3333333333333333333333333333333333333333"]
+            .pnl
+            .as_ref()
+            .unwrap();
+        assert_eq!(c.unrealized_pnl_usd.unwrap().round_dp(2), Decimal::from(25));
+
+        let ranked = rank_traders(stats, SortKey::Pnl, SortOrder::Desc);
+        assert_eq!(
+            ranked[0].address,
+            "0x1111111111111111111111111111111111111111"
+        );
+    }
+
+    #[test]
+    fn test_rank_traders_by_metric_with_address_tie_break() {
+        let stats =
+            aggregate_trader_stats(&fixture_swaps(), WETH, &AggregationOptions::default()).unwrap();
+        let addresses = |traders: Vec<TraderStats>| {
+            traders
+                .into_iter()
+                .map(|t| t.address[..4].to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let by_ratio = rank_traders(stats.clone(), SortKey::BuySellRatio, SortOrder::Desc);
+        assert_eq!(addresses(by_ratio), vec!["0x33", "0x11", "0x22"]);
+        let by_trades = rank_traders(stats, SortKey::Trades, SortOrder::Asc);
+        assert_eq!(addresses(by_trades), vec!["0x33", "0x22", "0x11"]);
+
+        let tied: HashMap<String, TraderStats> = ["0xbb", "0xaa", "0xcc"]
+            .into_iter()
+            .map(|address| (address.to_string(), TraderStats::new(address.to_string())))
+            .collect();
+        for order in [SortOrder::Asc, SortOrder::Desc] {
+            let ranked = rank_traders(tied.clone(), SortKey::Volume, order);
+            assert_eq!(addresses(ranked), vec!["0xaa", "0xbb", "0xcc"]);
+        }
+    }
+
+    #[test]
+    fn test_api_buy_sell_ratio_follows_ranking() {
+        let trader = |address: &str, buys: u32, sells: u32| {
+            let stats = TraderStats {
+                total_buys: buys,
+                total_sells: sells,
+                ..TraderStats::new(address.to_string())
+            };
+            (address.to_string(), stats)
+        };
+        // A buy-only trader with 3 buys outranks one with 5 buys per sell
+        let stats = HashMap::from([
+            trader("0xaa", 3, 0),
+            trader("0xbb", 5, 1),
+            trader("0xcc", 2, 1),
+        ]);
+
+        let labels = LabelRegistry::default();
+        let ratios: Vec<(String, Option<f64>)> =
+            rank_traders(stats, SortKey::BuySellRatio, SortOrder::Desc)
+                .into_iter()
+                .map(|trader| {
+                    let row = crate::types::TraderStatsAPI::from_stats(trader, &labels);
+                    (row.address, row.buy_sell_ratio)
+                })
+                .collect();
+        assert_eq!(
+            ratios,
+            vec![
+                ("0xaa".to_string(), None),
+                ("0xbb".to_string(), Some(5.0)),
+                ("0xcc".to_string(), Some(2.0)),
+            ]
+        );
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_time_bound_dates() {
+        assert_eq!(
+            parse_time_bound("2024-05-01", TimeBound::Start, now()).unwrap(),
+            1_714_521_600
+        );
+        assert_eq!(
+            parse_time_bound("2024-05-31", TimeBound::End, now()).unwrap(),
+            1_717_199_999
+        );
+        assert_eq!(
+            parse_time_bound("2024-05-01T06:30:00+02:00", TimeBound::Start, now()).unwrap(),
+            1_714_537_800
+        );
+        assert_eq!(
+            parse_time_bound("1714521600", TimeBound::End, now()).unwrap(),
+            1_714_521_600
+        );
+    }
+
+    #[test]
+    fn test_parse_time_bound_relative() {
+        let now_ts = now().timestamp() as u64;
+        assert_eq!(
+            parse_time_bound("7d", TimeBound::Start, now()).unwrap(),
+            now_ts - 7 * 86_400
+        );
+        assert_eq!(
+            parse_time_bound("24h", TimeBound::Start, now()).unwrap(),
+            now_ts - 86_400
+        );
+        assert!(parse_time_bound("7y", TimeBound::Start, now()).is_err());
+        // Out of range durations are errors rather than panics
+        assert!(parse_time_bound("99999999999d", TimeBound::Start, now()).is_err());
+        assert!(parse_time_bound("99999999999999s", TimeBound::Start, now()).is_err());
+        assert!(parse_time_bound("last week", TimeBound::Start, now()).is_err());
+    }
+}